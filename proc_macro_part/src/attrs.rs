@@ -0,0 +1,100 @@
+use syn::{Attribute, Field, LitStr, Path};
+
+// What `#[touhou(...)]` says about the whole struct
+#[derive(Default)]
+pub struct ContainerOptions {
+    // Properties no field reads are an error instead of being ignored
+    pub deny_unknown: bool,
+    // The object read, `None` for the globals and the lowercased struct name when not given
+    pub object: Option<Option<String>>,
+}
+
+impl ContainerOptions {
+    pub fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = ContainerOptions::default();
+
+        for attr in touhou_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("deny_unknown") {
+                    options.deny_unknown = true;
+                } else if meta.path.is_ident("root") || meta.path.is_ident("object") {
+                    if options.object.is_some() {
+                        return Err(meta.error("only one of `root` and `object` can be given"));
+                    }
+
+                    options.object = match meta.path.is_ident("root") {
+                        true => Some(None),
+                        false => Some(Some(
+                            meta.value()?.parse::<LitStr>()?.value().to_lowercase(),
+                        )),
+                    };
+                } else {
+                    return Err(meta.error("expected `deny_unknown`, `root` or `object`"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+// What `#[touhou(...)]` says about a field
+#[derive(Default)]
+pub struct FieldOptions {
+    // The properties read into the field, its own name unless renamed, then any aliases
+    pub keys: Vec<String>,
+    // Set when the property is missing, `Some(None)` being `Default::default()`
+    pub default: Option<Option<Path>>,
+    pub skip: bool,
+    pub required: bool,
+}
+
+// Properties are written as single lowercase words, `shoot pattern` being `shoot_pattern`
+fn property(name: &str) -> String {
+    name.trim().to_lowercase().replace(' ', "_")
+}
+
+impl FieldOptions {
+    pub fn parse(field: &Field, name: &str) -> syn::Result<Self> {
+        let mut options = FieldOptions::default();
+        let mut rename = None;
+
+        for attr in touhou_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    rename = Some(property(&lit.value()));
+                } else if meta.path.is_ident("alias") {
+                    let lit: LitStr = meta.value()?.parse()?;
+                    options.keys.push(property(&lit.value()));
+                } else if meta.path.is_ident("default") {
+                    options.default = match meta.input.peek(syn::Token![=]) {
+                        true => Some(Some(meta.value()?.parse::<LitStr>()?.parse()?)),
+                        false => Some(None),
+                    };
+                } else if meta.path.is_ident("skip") {
+                    options.skip = true;
+                } else if meta.path.is_ident("required") {
+                    options.required = true;
+                } else {
+                    return Err(
+                        meta.error("expected `rename`, `alias`, `default`, `skip` or `required`")
+                    );
+                }
+
+                Ok(())
+            })?;
+        }
+
+        options
+            .keys
+            .insert(0, rename.unwrap_or_else(|| property(name)));
+        Ok(options)
+    }
+}
+
+fn touhou_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("touhou"))
+}
@@ -1,7 +1,248 @@
+mod attrs;
+
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+
+use attrs::{ContainerOptions, FieldOptions};
+use syn::{ext::IdentExt, parse_macro_input, parse_quote, Data, DeriveInput, Fields};
 
-#[proc_macro_derive(Evaluate)]
+#[proc_macro_derive(Evaluate, attributes(touhou))]
 pub fn proc_macro_derive(token_stream: TokenStream) -> TokenStream {
-    let code = token_stream.to_string();
-    format!("evaluate_derive! {{ {code} }}").parse().unwrap()
+    let input = parse_macro_input!(token_stream as DeriveInput);
+
+    derive_evaluate(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn derive_evaluate(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            fields => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "Evaluate can only be derived for structs with named fields",
+                ))
+            }
+        },
+        Data::Enum(data) => {
+            return Err(syn::Error::new_spanned(
+                data.enum_token,
+                "Evaluate can only be derived for structs, use `impl_enum!` for enums",
+            ))
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new_spanned(
+                data.union_token,
+                "Evaluate can only be derived for structs",
+            ))
+        }
+    };
+
+    let container = ContainerOptions::parse(&input.attrs)?;
+    let name = &input.ident;
+
+    let mut idents = vec![];
+    let mut types = vec![];
+    let mut options = vec![];
+    let mut skipped = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let field_options = FieldOptions::parse(field, &ident.unraw().to_string())?;
+
+        if field_options.skip {
+            skipped.push((ident, field_options));
+        } else {
+            idents.push(ident);
+            types.push(&field.ty);
+            options.push(field_options);
+        }
+    }
+
+    let keys: Vec<_> = options.iter().map(|o| &o.keys[0]).collect();
+    let patterns: Vec<_> = options
+        .iter()
+        .map(|o| {
+            let keys = &o.keys;
+            quote! { #(#keys)|* }
+        })
+        .collect();
+    let all_keys: Vec<_> = options.iter().flat_map(|o| &o.keys).collect();
+    let count = idents.len();
+
+    let unknown = match container.deny_unknown {
+        true => quote! {
+            key => {
+                let properties = [#(#all_keys),*];
+                return Err(::touhoulang::evaluator::EvalError::unknown_property(key, &properties));
+            }
+        },
+        false => quote! { _ => {} },
+    };
+
+    let required = idents
+        .iter()
+        .zip(&options)
+        .enumerate()
+        .filter(|(_, (_, o))| o.required)
+        .map(|(i, (_, o))| {
+            let key = &o.keys[0];
+            quote! {
+                if !seen[#i] {
+                    return Err(::touhoulang::evaluator::EvalError::missing(#key));
+                }
+            }
+        });
+
+    // Only a new value starts from the field's default, evaluating again keeps what is there
+    let defaults = idents
+        .iter()
+        .zip(&options)
+        .enumerate()
+        .filter_map(|(i, (ident, o))| {
+            let value = default_value(o)?;
+
+            Some(quote! {
+                if !seen[#i] {
+                    value.#ident = #value;
+                }
+            })
+        });
+
+    // A new value takes what was written, then the field's default, and only fails without one
+    let fallbacks = types.iter().zip(&options).map(|(ty, o)| {
+        let key = &o.keys[0];
+        match default_value(o) {
+            _ if o.required => {
+                quote! { return Err(::touhoulang::evaluator::EvalError::missing(#key)) }
+            }
+            Some(value) => value,
+            None => quote! {
+                <#ty as ::touhoulang::evaluator::FromValue>::missing()
+                    .ok_or_else(|| ::touhoulang::evaluator::EvalError::missing(#key))?
+            },
+        }
+    });
+
+    // Skipped fields are never read, so they always start from their default
+    let skipped_idents = skipped.iter().map(|(ident, _)| ident);
+    let skipped_values = skipped.iter().map(|(_, o)| {
+        default_value(o).unwrap_or_else(|| quote! { ::std::default::Default::default() })
+    });
+
+    // Every field type has to be readable, which is only worth spelling out for generic ones
+    let mut generics = input.generics.clone();
+    let predicates = &mut generics.make_where_clause().predicates;
+    for ty in &types {
+        predicates.push(parse_quote!(#ty: ::touhoulang::evaluator::FromValue));
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let object = match container.object {
+        Some(None) => quote! { None },
+        Some(Some(object)) => quote! { Some(#object.to_string()) },
+        None => {
+            let object = name.unraw().to_string().to_lowercase();
+            quote! { Some(#object.to_string()) }
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::touhoulang::evaluator::Evaluate for #name #ty_generics #where_clause {
+            fn object() -> Option<String> {
+                #object
+            }
+
+            fn evaluate_properties(
+                &mut self,
+                map: ::touhoulang::parser::Object,
+            ) -> Result<(), ::touhoulang::evaluator::EvalError> {
+                for (key, value) in map.into_iter() {
+                    match key.as_str() {
+                        #(#patterns => {
+                            self.#idents = <#types as ::touhoulang::evaluator::FromValue>::from_value(value)
+                                .map_err(|e| e.in_field(#keys))?;
+                        })*
+                        #unknown
+                    }
+                }
+
+                Ok(())
+            }
+
+            fn from_properties(
+                map: ::touhoulang::parser::Object,
+            ) -> Result<Self, ::touhoulang::evaluator::EvalError> {
+                // A struct with its own `Default` keeps it for whatever isn't written
+                if let Some(mut value) = ::touhoulang::default_of!(Self) {
+                    #[allow(unused_variables)]
+                    let seen: [bool; #count] =
+                        [#(map.keys().any(|key| matches!(key.as_str(), #patterns))),*];
+                    ::touhoulang::evaluator::Evaluate::evaluate_properties(&mut value, map)?;
+
+                    #(#required)*
+                    #(#defaults)*
+
+                    return Ok(value);
+                }
+
+                #(let mut #idents: Option<#types> = None;)*
+
+                for (key, value) in map.into_iter() {
+                    match key.as_str() {
+                        #(#patterns => {
+                            let value = <#types as ::touhoulang::evaluator::FromValue>::from_value(value)
+                                .map_err(|e| e.in_field(#keys))?;
+                            #idents = Some(value);
+                        })*
+                        #unknown
+                    }
+                }
+
+                Ok(Self {
+                    #(#idents: match #idents {
+                        Some(value) => value,
+                        None => #fallbacks,
+                    },)*
+                    #(#skipped_idents: #skipped_values,)*
+                })
+            }
+        }
+
+        impl #impl_generics ::touhoulang::evaluator::FromValue for #name #ty_generics #where_clause {
+            fn from_value(
+                value: ::touhoulang::parser::Value,
+            ) -> Result<Self, ::touhoulang::evaluator::EvalError> {
+                match value {
+                    ::touhoulang::parser::Value::Object(map) => {
+                        <Self as ::touhoulang::evaluator::Evaluate>::from_properties(map)
+                    }
+                    d => Err(::touhoulang::evaluator::EvalError::mismatch::<Self>(d)),
+                }
+            }
+
+            fn missing() -> Option<Self> {
+                let map = ::touhoulang::parser::Object::new();
+                <Self as ::touhoulang::evaluator::Evaluate>::from_properties(map).ok()
+            }
+        }
+
+        impl #impl_generics ::std::str::FromStr for #name #ty_generics #where_clause {
+            type Err = ::touhoulang::evaluator::EvalError;
+
+            fn from_str(code: &str) -> Result<Self, Self::Err> {
+                <Self as ::touhoulang::evaluator::Evaluate>::try_from_str(code)
+            }
+        }
+    })
+}
+
+// What a missing field is set to, if it has a default
+fn default_value(options: &FieldOptions) -> Option<TokenStream2> {
+    match options.default.as_ref()? {
+        Some(path) => Some(quote! { #path() }),
+        None => Some(quote! { ::std::default::Default::default() }),
+    }
 }
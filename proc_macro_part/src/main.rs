@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use touhoulang::*;
+use touhoulang::evaluator::Evaluate;
 use touhoulang_macro::Evaluate;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -47,7 +47,7 @@ fn main() {
         let reimu: ReimuSerde = serde_json::from_str(&data).expect("Failed to deserialize data");
         println!("{reimu:?}");
     } else if serializer == "touhoulang" {
-        let reimu = Reimu::from_str(&data);
+        let reimu = Reimu::from_text(&data);
         println!("{reimu:?}");
     } else {
         panic!("Expected serializer name: 'serde' or 'touhoulang'");
@@ -1,5 +1,7 @@
+use std::{collections::HashMap, num::NonZeroU32};
+
+use touhoulang::evaluator::Evaluate;
 use touhoulang_macro::Evaluate;
-use touhoulang::*;
 
 #[derive(Evaluate, Default)]
 struct Reimu {
@@ -7,9 +9,213 @@ struct Reimu {
     damage: f32,
 }
 
+/// Loaded from other modules, so everything is public
+#[derive(Evaluate, Default, Debug, PartialEq)]
+pub struct Marisa {
+    /// How the stage greets her
+    pub name: std::string::String,
+    pub spells: HashMap<String, Vec<i32>>,
+    pub r#type: String,
+}
+
+#[derive(Evaluate, Default)]
+struct Stage<T> {
+    boss: T,
+}
+
+#[derive(Evaluate, Default)]
+struct Enemy {
+    #[touhou(rename = "health", alias = "hp")]
+    life: i32,
+    #[touhou(rename = "shoot pattern")]
+    pattern: Vec<String>,
+    #[touhou(default = "default_speed")]
+    speed: f32,
+    #[touhou(skip)]
+    frames: u32,
+}
+
+#[derive(Evaluate, Default, Debug)]
+#[touhou(deny_unknown)]
+struct Boss {
+    #[touhou(required)]
+    health: i32,
+    #[touhou(alias = "spellcards")]
+    spells: Vec<String>,
+}
+
+// `cost` has no default, so it has to be written
+#[derive(Evaluate, Debug)]
+struct Spellcard {
+    name: String,
+    cost: NonZeroU32,
+    #[touhou(default)]
+    bonus: u32,
+}
+
+fn default_speed() -> f32 {
+    2.5
+}
+
+#[derive(Evaluate, Default)]
+#[touhou(root)]
+struct Settings {
+    title: String,
+}
+
 #[test]
 fn parse_derive() {
-    let reimu = Reimu::from_str("The age of Reimu is 18, and her damage is 12.5"); // FIX: doesn't matter capital letters
+    let reimu = Reimu::from_text("The age of Reimu is 18, and her damage is 12.5"); // FIX: doesn't matter capital letters
     assert_eq!(reimu.age, 18);
     assert_eq!(reimu.damage, 12.5);
 }
+
+#[test]
+fn derives_public_structs_with_paths_and_raw_names() {
+    let marisa: Marisa = r#"marisa's name is "Marisa", and her type is "witch"."#
+        .parse()
+        .unwrap();
+
+    assert_eq!(marisa.name, "Marisa");
+    assert_eq!(marisa.r#type, "witch");
+    assert!(marisa.spells.is_empty());
+}
+
+#[test]
+fn derives_generic_structs() {
+    let stage =
+        Stage::<Marisa>::from_text(r#"the stage's boss is marisa, and marisa's name is "Marisa"."#);
+
+    assert_eq!(stage.boss.name, "Marisa");
+}
+
+#[test]
+fn defaults_missing_generic_fields() {
+    let stage = Stage::<Marisa>::try_from_str("the stage's x is 1").unwrap();
+
+    assert_eq!(stage.boss, Marisa::default());
+
+    let err = Stage::<Spellcard>::try_from_str("the stage's x is 1").err();
+
+    assert_eq!(
+        err.map(|e| e.to_string()),
+        Some("stage.boss: Missing required property `boss`".to_string())
+    );
+}
+
+#[test]
+fn derives_globals() {
+    let settings = Settings::from_text(r#"the title is "The search of something""#);
+
+    assert_eq!(settings.title, "The search of something");
+}
+
+#[test]
+fn reads_the_chosen_object() {
+    #[derive(Evaluate, Debug)]
+    #[touhou(object = "Alice")]
+    struct Puppeteer {
+        name: String,
+    }
+
+    #[derive(Evaluate, Debug)]
+    #[touhou(object = "patchouli")]
+    struct Librarian {
+        name: String,
+    }
+
+    let text = r#"alice's name is "Alice", and patchouli's name is "Patchouli""#;
+
+    assert_eq!(Puppeteer::from_text(text).name, "Alice");
+    assert_eq!(Librarian::from_text(text).name, "Patchouli");
+}
+
+#[test]
+fn reports_field_paths() {
+    let err = Stage::<Marisa>::try_from_str("the stage's boss is 12").err();
+
+    assert_eq!(
+        err.map(|e| e.to_string()),
+        Some("stage.boss: Expected Marisa but found Number(12.0)".to_string())
+    );
+}
+
+#[test]
+fn renames_and_aliases_fields() {
+    let enemy =
+        Enemy::from_text(r#"the enemy's hp is 10, and its shoot_pattern are "left" "right"."#);
+
+    assert_eq!(enemy.life, 10);
+    assert_eq!(enemy.pattern, ["left", "right"]);
+
+    let enemy = Enemy::from_text("the enemy's health is 20");
+
+    assert_eq!(enemy.life, 20);
+}
+
+#[test]
+fn defaults_and_skips_fields() {
+    let mut enemy = Enemy::from_text("the enemy's frames is 60, and its life is 3");
+
+    assert_eq!(enemy.speed, 2.5);
+    assert_eq!(enemy.frames, 0);
+    assert_eq!(enemy.life, 0);
+
+    enemy.evaluate_text("the enemy's speed is 4");
+    assert_eq!(enemy.speed, 4.0);
+
+    enemy.evaluate_text("the enemy's health is 5");
+    assert_eq!(enemy.speed, 4.0);
+    assert_eq!(enemy.life, 5);
+}
+
+#[test]
+fn denies_unknown_properties() {
+    let err = Boss::try_from_str("the boss's helth is 5").unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "boss.helth: Unknown property `helth`, did you mean `health`?"
+    );
+
+    let err = Boss::try_from_str("the boss's health is 5, and her color is \"red\"").unwrap_err();
+
+    assert_eq!(err.to_string(), "boss.color: Unknown property `color`");
+}
+
+#[test]
+fn requires_fields() {
+    let err = Boss::try_from_str("the boss's spellcards are \"spark\"").unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "boss.health: Missing required property `health`"
+    );
+    assert!(Boss::try_from_str("reimu's age is 17").is_err());
+    assert_eq!(
+        Boss::try_from_str("the boss's health is 5").unwrap().health,
+        5
+    );
+
+    let mut boss = Boss::from_text("the boss's health is 5");
+    boss.evaluate_text("the boss's spellcards are \"spark\"");
+    assert_eq!(boss.health, 5);
+}
+
+#[test]
+fn builds_structs_without_default() {
+    let card = Spellcard::from_text(r#"the spellcard's name is "Master Spark", and its cost is 2"#);
+
+    assert_eq!(card.name, "Master Spark");
+    assert_eq!(card.cost.get(), 2);
+    assert_eq!(card.bonus, 0);
+
+    let err = Spellcard::try_from_str(r#"the spellcard's name is "Spark""#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "spellcard.cost: Missing required property `cost`"
+    );
+
+    let err = Spellcard::try_from_str(r#"the spellcard's name is "Spark", and its cost is 0"#);
+    assert!(err.is_err());
+}
@@ -1,147 +1,847 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+    num::{
+        NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU16, NonZeroU32,
+        NonZeroU64, NonZeroU8, NonZeroUsize,
+    },
+    path::PathBuf,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    parser::{self, Diagnostic, Kinds, Object, Unit, Value},
+    tokenizer,
+};
+
+// Turns a parsed value into a field, implement it to read your own types
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self, EvalError>;
+
+    // What a field of this type is when its property is missing, `None` making it required
+    fn missing() -> Option<Self> {
+        None
+    }
+}
+
+// A type read from a whole TouhouLang file, either the globals or the object named like it
+pub trait Evaluate: Sized {
+    // The object this type reads, `None` to read the globals
+    fn object() -> Option<String>;
+
+    // Sets the fields written in `map`, leaving the others as they are
+    fn evaluate_properties(&mut self, map: Object) -> Result<(), EvalError>;
+
+    // Builds a new value from `map`, only falling back to defaults for the missing fields
+    fn from_properties(map: Object) -> Result<Self, EvalError>;
+
+    // Takes the properties this type reads out of everything parsed
+    fn properties(mut objs: Object) -> Result<Object, EvalError> {
+        let Some(name) = Self::object() else {
+            return Ok(objs);
+        };
+
+        match objs.shift_remove(&name) {
+            Some(Value::Object(map)) => Ok(map),
+            Some(d) => Err(EvalError::mismatch::<Self>(d).in_field(&name)),
+            None => Ok(Object::new()),
+        }
+    }
+
+    fn try_evaluate(&mut self, objs: Object) -> Result<(), EvalError> {
+        let map = Self::properties(objs)?;
+        self.evaluate_properties(map).map_err(in_object::<Self>)
+    }
+
+    fn try_from_objects(objs: Object) -> Result<Self, EvalError> {
+        let map = Self::properties(objs)?;
+        Self::from_properties(map).map_err(in_object::<Self>)
+    }
+
+    fn evaluate(&mut self, objs: Object) {
+        self.try_evaluate(objs).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    fn evaluate_text(&mut self, text: &str) {
+        self.evaluate(parser::parse(tokenizer::tokenize(text)));
+    }
+
+    fn try_from_str(code: &str) -> Result<Self, EvalError> {
+        Self::try_from_objects(parse(code)?.0)
+    }
+
+    // Named so it doesn't clash with `FromStr::from_str`, which returns the error instead
+    fn from_text(code: &str) -> Self {
+        Self::try_from_str(code).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // Reads what was written `in <section>: ...` instead of the top level, the globals there
+    // for a root type and its own object inside the section for any other
+    fn try_from_section(code: &str, section: &str) -> Result<Self, EvalError> {
+        let (mut objs, _) = parse(code)?;
+        let section = section.to_lowercase();
+
+        let map = match objs.shift_remove(&section) {
+            Some(Value::Object(map)) => map,
+            Some(d) => return Err(EvalError::mismatch::<Self>(d).in_field(&section)),
+            None => Object::new(),
+        };
+
+        Self::try_from_objects(map).map_err(|e| e.in_field(&section))
+    }
+
+    fn from_section(code: &str, section: &str) -> Self {
+        Self::try_from_section(code, section).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // Every top level object declared as this type's object, `fairy is an enemy` for `Enemy`
+    fn try_all_from_str(code: &str) -> Result<HashMap<String, Self>, EvalError> {
+        let (objs, kinds) = parse(code)?;
+        let Some(kind) = Self::object() else {
+            return Ok(HashMap::new());
+        };
+
+        objs.into_iter()
+            .filter_map(|(name, value)| match value {
+                Value::Object(map) if kinds.get(&name).is_some_and(|k| k.contains(&kind)) => {
+                    Some((name, map))
+                }
+                // Nothing else was said about it, so it's just a word naming the kind
+                Value::Word(word) if word == kind => Some((name, Object::new())),
+                _ => None,
+            })
+            .map(from_named)
+            .collect()
+    }
+
+    fn all_from_str(code: &str) -> HashMap<String, Self> {
+        Self::try_all_from_str(code).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    // Every top level object whose properties `matches` accepts, by name
+    fn try_all_matching<F>(code: &str, mut matches: F) -> Result<HashMap<String, Self>, EvalError>
+    where
+        F: FnMut(&str, &Object) -> bool,
+    {
+        let (objs, _) = parse(code)?;
+
+        objs.into_iter()
+            .filter_map(|(name, value)| match value {
+                Value::Object(map) if matches(&name, &map) => Some((name, map)),
+                _ => None,
+            })
+            .map(from_named)
+            .collect()
+    }
+
+    fn all_matching<F>(code: &str, matches: F) -> HashMap<String, Self>
+    where
+        F: FnMut(&str, &Object) -> bool,
+    {
+        Self::try_all_matching(code, matches).unwrap_or_else(|e| panic!("{e}"))
+    }
+}
+
+// Parses `code`, reporting the first thing wrong with it as the error of the property it's in
+fn parse(code: &str) -> Result<(Object, Kinds), EvalError> {
+    parser::parse_kinds(tokenizer::tokenize(code)).map_err(|diagnostics| {
+        match diagnostics.into_iter().next().unwrap() {
+            Diagnostic::Undefined { path, name } => EvalError {
+                path: path.join("."),
+                kind: ErrorKind::Undefined(name),
+            },
+            Diagnostic::Mismatch {
+                path,
+                expected,
+                found,
+                ..
+            } => EvalError {
+                path: path.join("."),
+                kind: ErrorKind::Mismatch { expected, found },
+            },
+        }
+    })
+}
+
+fn from_named<T: Evaluate>((name, map): (String, Object)) -> Result<(String, T), EvalError> {
+    let value = T::from_properties(map).map_err(|e| e.in_field(&name))?;
+    Ok((name, value))
+}
+
+fn in_object<T: Evaluate>(e: EvalError) -> EvalError {
+    match T::object() {
+        Some(name) => e.in_field(&name),
+        None => e,
+    }
+}
+
+// Picks `T::default()` when `T` has one, which the generated code calls as
+// `(&DefaultOf::<T>(PhantomData)).default_value()` with both traits in scope. The choice is made
+// where that call is written, so a generic `T` never counts as having one
+#[doc(hidden)]
+pub struct DefaultOf<T>(pub std::marker::PhantomData<T>);
+
+#[doc(hidden)]
+pub trait WithDefault<T> {
+    fn default_value(&self) -> Option<T>;
+}
+
+impl<T: Default> WithDefault<T> for DefaultOf<T> {
+    fn default_value(&self) -> Option<T> {
+        Some(T::default())
+    }
+}
+
+#[doc(hidden)]
+pub trait WithoutDefault<T> {
+    fn default_value(&self) -> Option<T>;
+}
+
+impl<T> WithoutDefault<T> for &DefaultOf<T> {
+    fn default_value(&self) -> Option<T> {
+        None
+    }
+}
+
+// `Some(<$ty>::default())` if the type implements `Default`, `None` otherwise
+#[doc(hidden)]
 #[macro_export]
-macro_rules! parse_number {
-    ($value: expr, $num: ty) => {
-        match $value {
-            $crate::parser::Value::String(s) => s.parse().expect("Expected number"),
-            $crate::parser::Value::Number(n) => n as $num,
-            _ => panic!("Expected string or number"),
+macro_rules! default_of {
+    ($ty: ty) => {{
+        #[allow(unused_imports)]
+        use $crate::evaluator::{WithDefault as _, WithoutDefault as _};
+
+        (&$crate::evaluator::DefaultOf::<$ty>(std::marker::PhantomData)).default_value()
+    }};
+}
+
+// Why a value couldn't be turned into a field, and which field that was
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvalError {
+    // Like `reimu.friend.nums[2]`, empty for the value itself
+    pub path: String,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    // `expected` is the Rust type the value was read as
+    Mismatch {
+        expected: String,
+        found: Value,
+    },
+    Undefined(String),
+    UnknownVariant {
+        expected: String,
+        variants: &'static [&'static str],
+        found: String,
+    },
+    // A property no field reads, with the closest one that does
+    UnknownProperty {
+        found: String,
+        suggestion: Option<String>,
+    },
+    Missing(String),
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Mismatch { expected, found } => {
+                write!(f, "Expected {expected} but found {found:?}")
+            }
+            ErrorKind::Undefined(name) => write!(f, "Undefined name `{name}`"),
+            ErrorKind::UnknownVariant {
+                expected,
+                variants,
+                found,
+            } => write!(
+                f,
+                "Expected one of {} for {expected} but found `{found}`",
+                variants.join(", ")
+            ),
+            ErrorKind::UnknownProperty { found, suggestion } => match suggestion {
+                Some(suggestion) => {
+                    write!(
+                        f,
+                        "Unknown property `{found}`, did you mean `{suggestion}`?"
+                    )
+                }
+                None => write!(f, "Unknown property `{found}`"),
+            },
+            ErrorKind::Missing(name) => write!(f, "Missing required property `{name}`"),
         }
-    };
+    }
 }
 
-#[macro_export(local_inner_macros)]
-macro_rules! parse_value {
-    ($value: expr, String) => {
-        match $value {
-            $crate::parser::Value::String(s) => s,
-            $crate::parser::Value::Number(n) => n.to_string(),
-            _ => std::panic!("Expected string or number"),
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.as_str() {
+            "" => write!(f, "{}", self.kind),
+            path => write!(f, "{path}: {}", self.kind),
         }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+impl From<ErrorKind> for EvalError {
+    fn from(kind: ErrorKind) -> Self {
+        EvalError {
+            path: String::new(),
+            kind,
+        }
+    }
+}
+
+impl EvalError {
+    // Words that didn't fit are names that were never defined
+    pub fn mismatch<T: ?Sized>(found: Value) -> Self {
+        match found {
+            Value::Word(name) => ErrorKind::Undefined(name).into(),
+            found => ErrorKind::Mismatch {
+                expected: type_name::<T>(),
+                found,
+            }
+            .into(),
+        }
+    }
+
+    // Puts the error inside the property `name`, as errors bubble up to the outermost object
+    pub fn in_field(mut self, name: &str) -> Self {
+        self.path = match self.path.chars().next() {
+            None | Some('[') => format!("{name}{}", self.path),
+            _ => format!("{name}.{}", self.path),
+        };
+        self
+    }
+
+    pub fn unknown_property(found: &str, properties: &[&str]) -> Self {
+        let suggestion = properties
+            .iter()
+            .map(|p| (distance(found, p), p))
+            .filter(|(d, p)| *d <= p.len().div_ceil(3))
+            .min_by_key(|(d, _)| *d)
+            .map(|(_, p)| p.to_string());
+
+        let kind = ErrorKind::UnknownProperty {
+            found: found.to_string(),
+            suggestion,
+        };
+        EvalError::from(kind).in_field(found)
+    }
+
+    pub fn missing(name: &str) -> Self {
+        EvalError::from(ErrorKind::Missing(name.to_string())).in_field(name)
+    }
+
+    pub fn in_index(mut self, index: usize) -> Self {
+        self.path = match self.path.chars().next() {
+            None | Some('[') => format!("[{index}]{}", self.path),
+            _ => format!("[{index}].{}", self.path),
+        };
+        self
+    }
+}
+
+// How many characters have to be added, removed or replaced to turn `a` into `b`
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let replaced = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+// The name of `T` without module paths, `Vec<String>` rather than `alloc::vec::Vec<alloc::string::String>`
+pub fn type_name<T: ?Sized>() -> String {
+    std::any::type_name::<T>()
+        .split_inclusive(|c: char| !(c.is_alphanumeric() || c == '_' || c == ':'))
+        .map(|part| part.rsplit("::").next().unwrap_or(part))
+        .collect()
+}
+
+macro_rules! impl_integer {
+    ($($num: ty),*) => {
+        $(impl FromValue for $num {
+            fn from_value(value: Value) -> Result<Self, EvalError> {
+                match value {
+                    Value::String(s) => s
+                        .parse()
+                        .map_err(|_| EvalError::mismatch::<$num>(Value::String(s))),
+                    // Rather than truncating `1.9` or saturating `300` into a `u8`. A quantity
+                    // gives its amount as written, `500 milliseconds` is 500 and not 0.5
+                    Value::Number(n) | Value::Quantity(n, _) if n.fract() == 0.0 => {
+                        <$num>::try_from(n as i128).map_err(|_| EvalError::mismatch::<$num>(value))
+                    }
+                    d => Err(EvalError::mismatch::<$num>(d)),
+                }
+            }
+
+            fn missing() -> Option<Self> {
+                Some(Self::default())
+            }
+        })*
     };
-    ($value: expr, i32) => {
-        parse_number!($value, i32)
-    };
-    ($value: expr, u32) => {
-        parse_number!($value, u32)
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+macro_rules! impl_float {
+    ($($num: ty),*) => {
+        $(impl FromValue for $num {
+            fn from_value(value: Value) -> Result<Self, EvalError> {
+                match value {
+                    Value::String(s) => s
+                        .parse()
+                        .map_err(|_| EvalError::mismatch::<$num>(Value::String(s))),
+                    // The amount as written, in whatever unit the quantity has
+                    Value::Number(n) | Value::Quantity(n, _) => Ok(n as $num),
+                    d => Err(EvalError::mismatch::<$num>(d)),
+                }
+            }
+
+            fn missing() -> Option<Self> {
+                Some(Self::default())
+            }
+        })*
     };
-    ($value: expr, f32) => {
-        parse_number!($value, f32)
+}
+
+impl_float!(f32, f64);
+
+macro_rules! impl_non_zero {
+    ($($num: ident($inner: ty)),*) => {
+        $(impl FromValue for $num {
+            fn from_value(value: Value) -> Result<Self, EvalError> {
+                let n = <$inner>::from_value(value.clone())?;
+                $num::new(n).ok_or_else(|| EvalError::mismatch::<$num>(value))
+            }
+        })*
     };
-    ($value: expr, $obj: tt) => {
-        match $value {
-            $crate::parser::Value::Object(map) => {
-                let map = std::collections::HashMap::from([(std::stringify!($obj).to_lowercase(), $value)]);
-                let mut obj = $obj::default();
-                obj.evaluate(map);
-                obj
+}
+
+impl_non_zero!(
+    NonZeroI8(i8),
+    NonZeroI16(i16),
+    NonZeroI32(i32),
+    NonZeroI64(i64),
+    NonZeroIsize(isize),
+    NonZeroU8(u8),
+    NonZeroU16(u16),
+    NonZeroU32(u32),
+    NonZeroU64(u64),
+    NonZeroUsize(usize)
+);
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        match value {
+            Value::String(s) => Ok(s),
+            Value::Number(n) => Ok(n.to_string()),
+            d => Err(EvalError::mismatch::<String>(d)),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        match value {
+            Value::String(s) | Value::Word(s) => match s.to_lowercase().as_str() {
+                // Bare `no` is taken by `has no`, so it can only be written as the string "no"
+                "true" | "yes" => Ok(true),
+                "false" | "no" => Ok(false),
+                _ => Err(EvalError::mismatch::<bool>(Value::String(s))),
             },
-            d => std::panic!("Expected an object got {d:?}"),
+            Value::Number(n) => Ok(n != 0.0),
+            d => Err(EvalError::mismatch::<bool>(d)),
         }
-    };
-    ($value: expr, Vec, $gtype: tt) => {
-        match $value {
-            $crate::parser::Value::List(ls) => ls
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl FromValue for char {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        let s = String::from_value(value)?;
+        let mut chars = s.chars();
+
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(EvalError::mismatch::<char>(Value::String(s))),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl FromValue for PathBuf {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        String::from_value(value).map(PathBuf::from)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl FromValue for Rc<str> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        String::from_value(value).map(Rc::from)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl FromValue for Arc<str> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        String::from_value(value).map(Arc::from)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+// Plain numbers are taken as seconds, frames are refused as their length depends on the frame rate
+impl FromValue for Duration {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        match value {
+            // Negative or too long to be a `Duration`
+            Value::Number(n) => Duration::try_from_secs_f32(n)
+                .map_err(|_| EvalError::mismatch::<Duration>(Value::Number(n))),
+            Value::Quantity(n, unit) => unit
+                .convert(n, Unit::Seconds)
+                .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+                .ok_or_else(|| EvalError::mismatch::<Duration>(Value::Quantity(n, unit))),
+            d => Err(EvalError::mismatch::<Duration>(d)),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl<T: FromValue> FromValue for Box<T> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        T::from_value(value).map(Box::new)
+    }
+
+    fn missing() -> Option<Self> {
+        T::missing().map(Box::new)
+    }
+}
+
+impl<T: FromValue> FromValue for Rc<T> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        T::from_value(value).map(Rc::new)
+    }
+
+    fn missing() -> Option<Self> {
+        T::missing().map(Rc::new)
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        match value {
+            Value::Nothing => Ok(None),
+            v => T::from_value(v).map(Some),
+        }
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        match value {
+            Value::List(ls) => ls
                 .into_iter()
-                .map(|v| $crate::parse_value!(v, $gtype))
+                .enumerate()
+                .map(|(i, v)| T::from_value(v).map_err(|e| e.in_index(i)))
                 .collect(),
-            _ => std::panic!("Expected list"),
+            d => Err(EvalError::mismatch::<Self>(d)),
         }
-    };
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+// Collects every property of an object, keyed by its name
+fn from_object<K, V, M>(value: Value) -> Result<M, EvalError>
+where
+    K: FromValue,
+    V: FromValue,
+    M: FromIterator<(K, V)>,
+{
+    match value {
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(k, v)| {
+                let value = V::from_value(v).map_err(|e| e.in_field(&k))?;
+                let key = K::from_value(Value::String(k.clone())).map_err(|e| e.in_field(&k))?;
+                Ok((key, value))
+            })
+            .collect(),
+        d => Err(EvalError::mismatch::<M>(d)),
+    }
+}
+
+impl<K: FromValue + Eq + Hash, V: FromValue> FromValue for HashMap<K, V> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        from_object(value)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
+}
+
+impl<K: FromValue + Ord, V: FromValue> FromValue for BTreeMap<K, V> {
+    fn from_value(value: Value) -> Result<Self, EvalError> {
+        from_object(value)
+    }
+
+    fn missing() -> Option<Self> {
+        Some(Self::default())
+    }
 }
 
 #[macro_export]
 macro_rules! set_field {
-    ($field: expr, $value: expr, $ftype:tt$(<$gtype:tt>)?) => {
-        $field = $crate::parse_value!($value, $ftype $(,$gtype)?);
+    ($field: expr, $value: expr, $ftype: ty, $name: expr) => {
+        $field = <$ftype as $crate::evaluator::FromValue>::from_value($value)
+            .map_err(|e| e.in_field($name))?;
     };
 }
 
 #[macro_export]
 macro_rules! impl_evaluate {
-    (Globals, $($field_name:ident: $ftype:tt$(<$gtype:tt>)?,)*) => {
-        impl Globals {
-            pub fn evaluate(&mut self, objs: $crate::parser::Object) {
-                for (key, value) in objs.into_iter() {
-                    match key.as_str() {
-                        $(stringify!($field_name) => {
-                            $crate::set_field!(self.$field_name, value, $ftype$(<$gtype>)?);
-                        })*
-                        _ => {},
-                    }
-                }
+    ($name: ident @root, $($field_name:ident: $ftype:ty,)*) => {
+        $crate::impl_evaluate!(@object $name, None, $($field_name: $ftype,)*);
+    };
+    ($name: ident @object $object: literal, $($field_name:ident: $ftype:ty,)*) => {
+        $crate::impl_evaluate!(@object $name, Some($object.to_lowercase()), $($field_name: $ftype,)*);
+    };
+    ($name: ident, $($field_name:ident: $ftype:ty,)*) => {
+        $crate::impl_evaluate!(
+            @object $name,
+            Some(stringify!($name).to_lowercase()),
+            $($field_name: $ftype,)*
+        );
+    };
+    (@object $name: ident, $object: expr, $($field_name:ident: $ftype:ty,)*) => {
+        impl $crate::evaluator::Evaluate for $name {
+            fn object() -> Option<String> {
+                $object
             }
+
+            $crate::impl_evaluate!(@properties $($field_name: $ftype,)*);
         }
     };
-    ($name: ident, $($field_name:ident: $ftype:tt$(<$gtype:tt>)?,)*) => {
-        impl $name {
-            pub fn evaluate(&mut self, objs: $crate::parser::Object) {
-                if let Some(obj) = objs.get(&stringify!($name).to_lowercase()) {
-                    match obj {
-                       $crate::parser::Value::Object(map) => {
-                           for (key, value) in map.into_iter() {
-                               match key.as_str() {
-                                   $(stringify!($field_name) => {
-                                       $crate::set_field!(self.$field_name, value.clone(), $ftype$(<$gtype>)?);
-                                   })*
-                                   _ => {},
-                               }
-                           }
-                       } ,
-                       tt => panic!("Expected {} to be a object but found {tt:?}!", stringify!($name)),
-                    }
+    (@properties $($field_name:ident: $ftype:ty,)*) => {
+        fn evaluate_properties(
+            &mut self,
+            map: $crate::parser::Object,
+        ) -> Result<(), $crate::evaluator::EvalError> {
+            for (key, value) in map.into_iter() {
+                match key.as_str() {
+                    $(stringify!($field_name) => {
+                        $crate::set_field!(self.$field_name, value, $ftype, stringify!($field_name));
+                    })*
+                    _ => {},
                 }
             }
+
+            Ok(())
+        }
+
+        fn from_properties(
+            map: $crate::parser::Object,
+        ) -> Result<Self, $crate::evaluator::EvalError> {
+            // A struct with its own `Default` keeps it for whatever isn't written
+            if let Some(mut value) = $crate::default_of!(Self) {
+                $crate::evaluator::Evaluate::evaluate_properties(&mut value, map)?;
+                return Ok(value);
+            }
+
+            $(let mut $field_name: Option<$ftype> = None;)*
+
+            for (key, value) in map.into_iter() {
+                match key.as_str() {
+                    $(stringify!($field_name) => {
+                        let value = <$ftype as $crate::evaluator::FromValue>::from_value(value)
+                            .map_err(|e| e.in_field(stringify!($field_name)))?;
+                        $field_name = Some(value);
+                    })*
+                    _ => {},
+                }
+            }
+
+            Ok(Self {
+                $($field_name: match $field_name {
+                    Some(value) => value,
+                    None => <$ftype as $crate::evaluator::FromValue>::missing()
+                        .ok_or_else(|| $crate::evaluator::EvalError::missing(stringify!($field_name)))?,
+                },)*
+            })
         }
     };
 }
 
 #[macro_export]
 macro_rules! impl_struct {
-    ($name: ident, $($field_name:ident: $ftype:tt$(<$gtype:tt>)?,)*) => {
-        $crate::impl_evaluate!($name, $($field_name: $ftype$(<$gtype>)?,)*);
+    ($name: ident $(@$option: ident $($object: literal)?)?, $($field_name:ident: $ftype:ty,)*) => {
+        $crate::impl_evaluate!($name $(@$option $($object)?)?, $($field_name: $ftype,)*);
+
+        impl $crate::evaluator::FromValue for $name {
+            fn from_value(
+                value: $crate::parser::Value,
+            ) -> Result<Self, $crate::evaluator::EvalError> {
+                match value {
+                    $crate::parser::Value::Object(map) => {
+                        <Self as $crate::evaluator::Evaluate>::from_properties(map)
+                    }
+                    d => Err($crate::evaluator::EvalError::mismatch::<Self>(d)),
+                }
+            }
 
-        impl $name {
-            #[allow(dead_code)]
-            fn evaluate_text(&mut self, text: &str) {
-                self.evaluate($crate::parser::parse($crate::tokenizer::tokenize(text)));
+            fn missing() -> Option<Self> {
+                <Self as $crate::evaluator::Evaluate>::from_properties($crate::parser::Object::new()).ok()
             }
+        }
 
-            #[allow(dead_code)]
-            fn from_str(code: &str) -> Self {
-                let mut me = Self::default();
-                me.evaluate_text(code);
-                me
+        impl std::str::FromStr for $name {
+            type Err = $crate::evaluator::EvalError;
+
+            fn from_str(code: &str) -> Result<Self, Self::Err> {
+                <Self as $crate::evaluator::Evaluate>::try_from_str(code)
             }
         }
     }
 }
 
+// Fills unit variants from words, `shoot_up`, `ShootUp` and `shootup` all being `ShootUp`
 #[macro_export]
-macro_rules! evaluate {
+macro_rules! impl_enum {
+    ($name: ident, $($variant:ident,)*) => {
+        impl $crate::evaluator::FromValue for $name {
+            fn from_value(
+                value: $crate::parser::Value,
+            ) -> Result<Self, $crate::evaluator::EvalError> {
+                let word = match value {
+                    $crate::parser::Value::Word(w) | $crate::parser::Value::String(w) => w,
+                    d => return Err($crate::evaluator::EvalError::mismatch::<Self>(d)),
+                };
+                let normal = word.replace('_', "").to_lowercase();
+
+                $(if normal == stringify!($variant).to_lowercase() {
+                    return Ok($name::$variant);
+                })*
+
+                Err($crate::evaluator::ErrorKind::UnknownVariant {
+                    expected: stringify!($name).to_string(),
+                    variants: &[$(stringify!($variant)),*],
+                    found: word,
+                }
+                .into())
+            }
+
+            fn missing() -> Option<Self> {
+                $crate::default_of!(Self)
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! evaluate_enum {
     (
     $(#[$doc:meta])*
-    struct $name: ident {
-        $($field_name:ident: $ftype:tt$(<$gtype:tt>)?,)*
+    enum $name: ident {
+        $($(#[$vdoc:meta])* $variant:ident,)*
     }
     ) => {
         $(#[$doc])*
-        #[derive(Default)]
-        struct $name {
-            $($field_name: $ftype$(<$gtype>)?,)*
+        enum $name {
+            $($(#[$vdoc])* $variant,)*
         }
 
-        $crate::impl_struct!($name, $($field_name: $ftype$(<$gtype>)?,)*);
+        $crate::impl_enum!($name, $($variant,)*);
     }
 }
 
+// `#[touhou(root)]` reads the globals and `#[touhou(object = "...")]` another object than the
+// one named like the struct, either of them written before any other attribute
 #[macro_export]
-macro_rules! evaluate_derive {
+macro_rules! evaluate {
+    (
+    #[touhou(root)]
+    $(#[$doc:meta])*
+    struct $name: ident {
+        $($field_name:ident: $ftype:ty,)*
+    }
+    ) => {
+        $(#[$doc])*
+        struct $name {
+            $($field_name: $ftype,)*
+        }
+
+        $crate::impl_struct!($name @root, $($field_name: $ftype,)*);
+    };
     (
+    #[touhou(object = $object: literal)]
     $(#[$doc:meta])*
     struct $name: ident {
-        $($field_name:ident: $ftype:tt$(<$gtype:tt>)?,)*
+        $($field_name:ident: $ftype:ty,)*
     }
     ) => {
-        $crate::impl_struct!($name, $($field_name: $ftype$(<$gtype>)?,)*);
+        $(#[$doc])*
+        struct $name {
+            $($field_name: $ftype,)*
+        }
+
+        $crate::impl_struct!($name @object $object, $($field_name: $ftype,)*);
+    };
+    (
+    $(#[$doc:meta])*
+    struct $name: ident {
+        $($field_name:ident: $ftype:ty,)*
     }
+    ) => {
+        $(#[$doc])*
+        struct $name {
+            $($field_name: $ftype,)*
+        }
+
+        $crate::impl_struct!($name, $($field_name: $ftype,)*);
+    };
 }
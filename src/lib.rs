@@ -2,13 +2,24 @@ pub mod parser;
 pub mod tokenizer;
 
 #[macro_use]
-mod evaluator;
+pub mod evaluator;
 
 #[cfg(test)]
 mod test_integration {
-    use crate::evaluate;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        path::PathBuf,
+        rc::Rc,
+        time::Duration,
+    };
+
+    use crate::{
+        evaluator::{ErrorKind, EvalError, Evaluate, FromValue},
+        parser::Value,
+    };
 
     evaluate! {
+        #[touhou(root)]
         struct Globals {
             age: i32,
         }
@@ -21,6 +32,19 @@ mod test_integration {
         }
     }
 
+    evaluate! {
+        struct Hero {
+            hp: i32,
+            lives: u8,
+        }
+    }
+
+    impl Default for Hero {
+        fn default() -> Self {
+            Self { hp: 100, lives: 3 }
+        }
+    }
+
     evaluate! {
         struct Marisa {
             age: i32,
@@ -28,6 +52,107 @@ mod test_integration {
         }
     }
 
+    evaluate! {
+        struct Bullet {
+            delay: Duration,
+            lifetime: Duration,
+            interval: Duration,
+            speed: f32,
+        }
+    }
+
+    evaluate_enum! {
+        #[derive(Debug, PartialEq, Default)]
+        enum Direction {
+            #[default]
+            Left,
+            Right,
+            ShootUp,
+        }
+    }
+
+    evaluate! {
+        struct Enemy {
+            pattern: Vec<Direction>,
+            facing: Direction,
+        }
+    }
+
+    evaluate! {
+        struct Boss {
+            title: Option<String>,
+            health: Option<i32>,
+            phase: Option<i32>,
+        }
+    }
+
+    evaluate! {
+        struct Roster {
+            enemies: HashMap<String, Enemy>,
+            prices: BTreeMap<String, i32>,
+        }
+    }
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Lives(u8);
+
+    impl FromValue for Lives {
+        fn from_value(value: Value) -> Result<Self, EvalError> {
+            match u8::from_value(value)? {
+                lives @ 0..=9 => Ok(Lives(lives)),
+                lives => Err(ErrorKind::Mismatch {
+                    expected: "at most 9 lives".to_string(),
+                    found: Value::Number(lives.into()),
+                }
+                .into()),
+            }
+        }
+    }
+
+    evaluate! {
+        struct Settings {
+            volume: u8,
+            score: i64,
+            scale: f64,
+            slots: usize,
+            fullscreen: bool,
+            key: char,
+            name: Rc<str>,
+            save: PathBuf,
+            lives: Lives,
+            player: Box<Reimu>,
+        }
+    }
+
+    evaluate! {
+        #[touhou(root)]
+        struct Chapter {
+            title: String,
+            level: i32,
+        }
+    }
+
+    evaluate! {
+        struct Stage {
+            title: String,
+            level: i32,
+        }
+    }
+
+    evaluate! {
+        #[touhou(object = "reimu")]
+        struct Player {
+            age: i32,
+        }
+    }
+
+    evaluate! {
+        #[touhou(object = "Marisa")]
+        struct Rival {
+            age: i32,
+        }
+    }
+
     #[test]
     fn integrates_from_start_to_finish() {
         let input = r#"
@@ -35,9 +160,9 @@ mod test_integration {
         the age of marisa is 18, and reimu's age is age, also reimu has an item item
         "#;
 
-        let globals = Globals::from_str(input);
-        let reimu = Reimu::from_str(input);
-        let marisa = Marisa::from_str(input);
+        let globals = Globals::from_text(input);
+        let reimu = Reimu::from_text(input);
+        let marisa = Marisa::from_text(input);
 
         assert_eq!(globals.age, 17);
 
@@ -46,4 +171,278 @@ mod test_integration {
 
         assert_eq!(marisa.age, 18);
     }
+
+    #[test]
+    fn forgotten_properties_fall_back_to_default() {
+        let input = r#"
+        reimu's age is 17, and reimu's item is "bow".
+        reimu has no item, also forget reimu's age
+        "#;
+
+        let reimu = Reimu::from_text(input);
+
+        assert_eq!(reimu.age, 0);
+        assert_eq!(reimu.item, "");
+
+        let hero = Hero::from_text("hero's hp is 5. forget hero's hp");
+
+        assert_eq!(hero.hp, 100);
+        assert_eq!(hero.lives, 3);
+    }
+
+    #[test]
+    fn overrides_prototype_properties() {
+        let input = r#"
+        marisa is like reimu, but her age is 18.
+        reimu's age is 17, and her items are "bow" "sword".
+        "#;
+
+        let marisa = Marisa::from_text(input);
+
+        assert_eq!(marisa.age, 18);
+        assert_eq!(marisa.items, vec!["bow".to_string(), "sword".to_string()]);
+    }
+
+    #[test]
+    fn reads_globals_from_sections() {
+        let input = r#"
+        in stage one: the title is "Lake", and the level is 1.
+        in stage two: the title is "Mansion", and the level is 2.
+        "#;
+
+        let one = Chapter::from_section(input, "stage one");
+        let two = Chapter::from_section(input, "Stage Two");
+
+        assert_eq!(one.title, "Lake");
+        assert_eq!(one.level, 1);
+        assert_eq!(two.title, "Mansion");
+        assert_eq!(two.level, 2);
+
+        let err = Chapter::try_from_section("in stage one: the level is \"high\".", "Stage One");
+        assert_eq!(
+            err.err().map(|e| e.to_string()),
+            Some("stage one.level: Expected i32 but found String(\"high\")".to_string())
+        );
+        assert!(Chapter::try_from_section("the lake is 1", "lake").is_err());
+
+        let reimu = Reimu::from_section("in stage: reimu's age is 3, and the age is 9.", "stage");
+        assert_eq!(reimu.age, 3);
+    }
+
+    #[test]
+    fn reads_the_object_chosen_by_attribute() {
+        let input = "reimu's age is 17, and marisa's age is 18";
+
+        let player = Player::from_text(input);
+        let rival = Rival::from_text(input);
+
+        assert_eq!(player.age, 17);
+        assert_eq!(rival.age, 18);
+        assert_eq!(
+            Rival::try_from_str("marisa's age is \"old\"")
+                .err()
+                .map(|e| e.path),
+            Some("marisa.age".to_string())
+        );
+    }
+
+    #[test]
+    fn fills_durations_from_quantities() {
+        let input = r#"
+        the bullet's delay is 250 milliseconds, its lifetime is 2 seconds, and its speed is 30 pixels.
+        the bullet's interval is every half a second.
+        "#;
+
+        let bullet = Bullet::from_text(input);
+
+        assert_eq!(bullet.delay, Duration::from_millis(250));
+        assert_eq!(bullet.lifetime, Duration::from_secs(2));
+        assert_eq!(bullet.interval, Duration::from_millis(500));
+        assert_eq!(bullet.speed, 30.0);
+
+        let err = Bullet::try_from_str("the bullet's interval is every 3 frames").err();
+        assert_eq!(
+            err.map(|e| e.to_string()),
+            Some("bullet.interval: Expected Duration but found Quantity(3.0, Frames)".to_string())
+        );
+
+        let err = Bullet::try_from_str("the bullet's delay is 100000000000000000000 seconds").err();
+        assert_eq!(err.map(|e| e.path), Some("bullet.delay".to_string()));
+
+        let bullet = Bullet::from_text("the bullet's speed is 500 milliseconds");
+        assert_eq!(bullet.speed, 500.0);
+    }
+
+    #[test]
+    fn fills_enums_from_words() {
+        let enemy = Enemy::from_text(
+            "the enemy's pattern are LEFT right shoot_up, and its facing is right",
+        );
+
+        assert_eq!(
+            enemy.pattern,
+            vec![Direction::Left, Direction::Right, Direction::ShootUp]
+        );
+        assert_eq!(enemy.facing, Direction::Right);
+
+        let enemy = Enemy::try_from_str("the enemy's pattern are left right").unwrap();
+        assert_eq!(enemy.pattern, vec![Direction::Left, Direction::Right]);
+    }
+
+    #[test]
+    fn loads_every_object_of_a_kind() {
+        let input = r#"
+        an enemy has a list of words called pattern, and a word called facing.
+        the fairy is an enemy, and its facing is left.
+        the wisp is an enemy, and its pattern are right right.
+        the ghost is an enemy.
+        the boss is like the fairy, but her facing is right.
+        reimu's age is 17.
+        "#;
+
+        let enemies = Enemy::all_from_str(input);
+
+        assert_eq!(enemies.len(), 4);
+        assert_eq!(enemies["fairy"].facing, Direction::Left);
+        assert_eq!(
+            enemies["wisp"].pattern,
+            [Direction::Right, Direction::Right]
+        );
+        assert_eq!(enemies["ghost"].facing, Direction::Left);
+        assert_eq!(enemies["boss"].facing, Direction::Right);
+
+        let aged = Reimu::try_all_matching(input, |_, obj| obj.contains_key("age")).unwrap();
+        assert_eq!(aged.keys().collect::<Vec<_>>(), ["reimu"]);
+        let wisps = Enemy::all_matching(input, |name, _| name.starts_with('w'));
+        assert_eq!(wisps.keys().collect::<Vec<_>>(), ["wisp"]);
+
+        let err = Enemy::try_all_from_str("the wisp is an enemy, and its facing is up");
+        assert_eq!(err.err().map(|e| e.path), Some("wisp.facing".to_string()));
+    }
+
+    #[test]
+    fn loads_objects_declared_as_a_defined_object() {
+        let input = r#"
+        the stage's level is 1.
+        the lake is a stage.
+        the mansion is a stage, its title is "Mansion", and its level is 2.
+        "#;
+
+        let stages = Stage::all_from_str(input);
+
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages["lake"].level, 1);
+        assert_eq!(stages["mansion"].title, "Mansion");
+        assert_eq!(stages["mansion"].level, 2);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Expected one of Left, Right, ShootUp for Direction but found `down`"
+    )]
+    fn lists_variants_on_unknown_words() {
+        Enemy::from_text("the enemy's facing is down");
+    }
+
+    #[test]
+    #[should_panic(expected = "Undefined name `sakuya`")]
+    fn reports_undefined_names() {
+        Reimu::from_text("reimu's item is sakuya");
+    }
+
+    #[test]
+    fn fills_options_unless_nothing() {
+        let boss = Boss::from_text("the boss's title is \"Flandre\", and her health is nothing");
+
+        assert_eq!(boss.title, Some("Flandre".to_string()));
+        assert_eq!(boss.health, None);
+        assert_eq!(boss.phase, None);
+    }
+
+    #[test]
+    fn collects_objects_into_maps() {
+        let input = r#"
+        in enemies: the fairy is an enemy, its facing is left,
+        the boss is an enemy, and its facing is right.
+        in prices: bow is 10, and sword is 20.
+        the roster's enemies is enemies, and its prices is prices.
+        "#;
+
+        let roster = Roster::from_text(input);
+
+        assert_eq!(roster.enemies.len(), 2);
+        assert_eq!(roster.enemies["fairy"].facing, Direction::Left);
+        assert_eq!(roster.enemies["boss"].facing, Direction::Right);
+        assert_eq!(
+            roster.prices,
+            BTreeMap::from([("bow".to_string(), 10), ("sword".to_string(), 20)])
+        );
+    }
+
+    #[test]
+    fn fills_every_primitive() {
+        let input = r#"
+        reimu's age is 17.
+        the volume of settings is 80, its score is 1000000, its scale is 1.5, its slots is 3.
+        its fullscreen is true, its key is "z", its name is "Reimu", its save is "save.dat".
+        its lives is 3, and its player is reimu.
+        "#;
+
+        let settings = Settings::from_text(input);
+
+        assert_eq!(settings.volume, 80);
+        assert_eq!(settings.score, 1000000);
+        assert_eq!(settings.scale, 1.5);
+        assert_eq!(settings.slots, 3);
+        assert!(settings.fullscreen);
+        assert_eq!(settings.key, 'z');
+        assert_eq!(&*settings.name, "Reimu");
+        assert_eq!(settings.save, PathBuf::from("save.dat"));
+        assert_eq!(settings.lives, Lives(3));
+        assert_eq!(settings.player.age, 17);
+    }
+
+    #[test]
+    fn reads_yes_and_no_as_booleans() {
+        let settings =
+            |fullscreen| format!("the settings's lives is 3, and its fullscreen is {fullscreen}");
+
+        assert!(Settings::from_text(&settings("yes")).fullscreen);
+        assert!(!Settings::from_text(&settings("\"no\"")).fullscreen);
+        assert!(Settings::try_from_str(&settings("\"maybe\"")).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected at most 9 lives but found Number(12.0)")]
+    fn reports_errors_from_custom_types() {
+        Settings::from_text("the settings's lives is 12");
+    }
+
+    #[test]
+    fn reports_declared_type_mismatches() {
+        let err =
+            Stage::try_from_str("a stage has a number called level. the stage's level is \"x\"");
+
+        assert_eq!(
+            err.err().map(|e| e.to_string()),
+            Some("stage.level: Expected a number but found String(\"x\")".to_string())
+        );
+    }
+
+    #[test]
+    fn parses_from_str() {
+        let reimu: Reimu = "reimu's age is 17, and her item is \"bow\""
+            .parse()
+            .unwrap();
+
+        assert_eq!(reimu.age, 17);
+        assert_eq!(reimu.item, "bow");
+        assert_eq!(
+            "reimu's item is sakuya".parse::<Reimu>().err(),
+            Some(EvalError {
+                path: "reimu.item".to_string(),
+                kind: ErrorKind::Undefined("sakuya".to_string()),
+            })
+        );
+    }
 }
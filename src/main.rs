@@ -1,11 +1,21 @@
-use touhoulang::{parser, tokenizer};
+use touhoulang::parser::{self, Lints};
 
 fn main() {
-    let filepath = std::env::args().nth(1).expect("Expected a file name");
-    let text = std::fs::read_to_string(&filepath).expect("Failed to read file");
+    let lint = std::env::args().any(|arg| arg == "--lint");
+    let filepath = std::env::args()
+        .skip(1)
+        .find(|arg| arg != "--lint")
+        .expect("Expected a file name");
 
-    let tokens = tokenizer::tokenize(&text);
-    let objs = parser::parse(tokens);
+    let lints = Lints {
+        redefinitions: lint,
+    };
+    let (objs, warnings) =
+        parser::parse_file_with(&filepath, |path| std::fs::read_to_string(path), lints);
+
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
 
     println!("{:#?}", objs);
 }
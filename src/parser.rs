@@ -1,6 +1,14 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io,
+    iter::Peekable,
+    path::{Component, Path, PathBuf},
+    slice::Iter,
+};
 
-use crate::tokenizer::Token;
+use indexmap::{IndexMap, IndexSet};
+
+use crate::tokenizer::{tokenize, Token};
 
 #[macro_export]
 macro_rules! val_str {
@@ -24,7 +32,7 @@ macro_rules! val_obj {
     ($name: literal, $($tuple: expr),*) => {
         (
             $name.to_string(),
-            $crate::parser::Value::Object(HashMap::from([$($tuple),*])),
+            $crate::parser::Value::Object($crate::parser::Object::from([$($tuple),*])),
         )
     };
 }
@@ -49,14 +57,15 @@ macro_rules! set_obj_property {
             }
         } else {
             $result.insert(
-                $name.to_string(),
-                Value::Object(HashMap::from([($property.to_string(), $value)])),
+                $name.to_lowercase(),
+                Value::Object(Object::from([($property.to_string(), $value)])),
             )
         };
     };
 }
 
-pub type Object = HashMap<String, Value>;
+// Keeps properties in the order they were written
+pub type Object = IndexMap<String, Value>;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
@@ -64,50 +73,669 @@ pub enum Value {
     String(String),
     List(Vec<Value>),
     Object(Object),
+    Reference(String),
+    Quantity(f32, Unit),
+    // A bare word that names nothing, like `left` in `her pattern are left right`
+    Word(String),
+    // Written `nothing` or `none`
+    Nothing,
+}
+
+// What a number written like `2 seconds` or `30 pixels` measures
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Unit {
+    Seconds,
+    Milliseconds,
+    // A time too, but how long one lasts depends on the frame rate, so it never converts to seconds
+    Frames,
+    Pixels,
+    Percent,
+    Degrees,
+    Radians,
+}
+
+impl Unit {
+    pub fn from_word(word: &str) -> Option<Unit> {
+        match word.to_lowercase().as_str() {
+            "second" | "seconds" => Some(Unit::Seconds),
+            "millisecond" | "milliseconds" => Some(Unit::Milliseconds),
+            "frame" | "frames" => Some(Unit::Frames),
+            "pixel" | "pixels" => Some(Unit::Pixels),
+            "percent" | "%" => Some(Unit::Percent),
+            "degree" | "degrees" => Some(Unit::Degrees),
+            "radian" | "radians" => Some(Unit::Radians),
+            _ => None,
+        }
+    }
+
+    // How many of this make one of the base unit of its kind, seconds for time and degrees for angles
+    fn per_base(self) -> f32 {
+        match self {
+            Unit::Milliseconds => 1000.0,
+            Unit::Radians => std::f32::consts::PI / 180.0,
+            _ => 1.0,
+        }
+    }
+
+    fn kind(self) -> Unit {
+        match self {
+            Unit::Milliseconds => Unit::Seconds,
+            Unit::Radians => Unit::Degrees,
+            unit => unit,
+        }
+    }
+
+    // Converts `value` of this unit into `to`, if both measure the same kind of thing
+    pub fn convert(self, value: f32, to: Unit) -> Option<f32> {
+        (self.kind() == to.kind()).then(|| value / self.per_base() * to.per_base())
+    }
+
+    pub fn is_time(self) -> bool {
+        matches!(self, Unit::Seconds | Unit::Milliseconds | Unit::Frames)
+    }
+}
+
+type Tokens<'a> = Peekable<Iter<'a, Token>>;
+
+// Maps an included path to its contents
+type FileResolver<'a> = dyn FnMut(&Path) -> io::Result<String> + 'a;
+
+// The kinds every object was declared as, `fairy is an enemy` being an enemy. Objects in a
+// section are keyed by their full name, `stage one's fairy`
+pub type Kinds = IndexMap<String, Vec<String>>;
+
+const POSSESSIVE_PRONOUNS: [&str; 4] = ["its", "her", "his", "their"];
+
+struct Parser<'a> {
+    result: Object,
+    resolver: &'a mut FileResolver<'a>,
+    includes: Vec<PathBuf>,
+    // The object talked about last, which is what pronouns refer to
+    subject: Option<String>,
+    // Objects declared to be `like` another one, by the name of that one
+    prototypes: IndexMap<Vec<String>, String>,
+    // Objects declared with `fairy is an enemy`, by the kind they were declared as
+    declared: IndexMap<Vec<String>, String>,
+    // The section sentences are written into until the next full stop
+    section: Option<String>,
+    sections: IndexSet<String>,
+    // Where every global and property was last set, to catch reassignments
+    definitions: HashMap<Vec<String>, Definition>,
+    // Properties declared for every kind of object, `an enemy has a number called health`
+    types: IndexMap<String, IndexMap<String, Type>>,
+    line: usize,
+    lints: Lints,
+    warnings: Vec<String>,
+}
+
+struct Definition {
+    location: String,
+    constant: bool,
+}
+
+// What a property declared with `<type> called <name>` may hold
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Number,
+    Word,
+    List(Box<Type>),
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Number => write!(f, "a number"),
+            Type::Word => write!(f, "a word"),
+            Type::List(ty) => write!(f, "a list of {}", ty.plural()),
+        }
+    }
+}
+
+impl Type {
+    fn plural(&self) -> String {
+        match self {
+            Type::Number => "numbers".to_string(),
+            Type::Word => "words".to_string(),
+            Type::List(ty) => format!("lists of {}", ty.plural()),
+        }
+    }
+
+    fn holds_words(&self) -> bool {
+        match self {
+            Type::Word => true,
+            Type::List(ty) => ty.holds_words(),
+            Type::Number => false,
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Type::Number, Value::Number(_) | Value::Quantity(..))
+            | (Type::Word, Value::String(_) | Value::Word(_)) => true,
+            (Type::List(ty), Value::List(ls)) => ls.iter().all(|v| ty.matches(v)),
+            // Any property may be left empty
+            (_, Value::Nothing) => true,
+            _ => false,
+        }
+    }
+}
+
+// What makes a document wrong, found once all of it has been collected
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    // A name used as a value without being defined anywhere, in the property at `path`
+    Undefined {
+        path: Vec<String>,
+        name: String,
+    },
+    // The property at `path` holds something else than every object of its kind declares
+    Mismatch {
+        path: Vec<String>,
+        expected: String,
+        kind: String,
+        found: Value,
+        location: String,
+    },
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::Undefined { path, name } => {
+                write!(f, "Undefined name `{name}` in {}", path.join("'s "))
+            }
+            Diagnostic::Mismatch {
+                path,
+                expected,
+                kind,
+                found,
+                location,
+            } => write!(
+                f,
+                "`{}` should be {expected} like every {kind}'s, but is {found:?} (set at {location})",
+                path.join("'s ")
+            ),
+        }
+    }
+}
+
+// Opt-in checks that report suspicious but valid sentences as warnings
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Lints {
+    pub redefinitions: bool,
 }
 
 pub fn parse(tokens: Vec<Token>) -> Object {
-    let mut tokens = tokens.iter().peekable();
-    let mut result = Object::new();
+    parse_with(tokens, Lints::default()).0
+}
+
+pub fn parse_with(tokens: Vec<Token>, lints: Lints) -> (Object, Vec<String>) {
+    let mut resolver = |path: &Path| std::fs::read_to_string(path);
+    let mut parser = Parser::new(&mut resolver, lints);
+
+    parser.collect(&tokens);
+    let (result, warnings, _) = parser.finish(true).unwrap_or_else(|e| report(&e));
+    (result, warnings)
+}
+
+// Parses `tokens` along with what every object is, so all objects of one kind can be found,
+// returning what is wrong with the document instead of panicking on it. Names that are
+// never defined are kept as words, since the type reading them knows if they are valid
+pub fn parse_kinds(tokens: Vec<Token>) -> Result<(Object, Kinds), Vec<Diagnostic>> {
+    let mut resolver = |path: &Path| std::fs::read_to_string(path);
+    let mut parser = Parser::new(&mut resolver, Lints::default());
+
+    parser.collect(&tokens);
+    let (result, _, kinds) = parser.finish(false)?;
+    Ok((result, kinds))
+}
+
+fn report(diagnostics: &[Diagnostic]) -> ! {
+    let diagnostics: Vec<_> = diagnostics.iter().map(|d| d.to_string()).collect();
+    panic!("{}", diagnostics.join("\n"));
+}
+
+// Parses the file at `path` asking `resolver` for its contents and the contents of
+// every file it includes, relative paths are taken from the including file
+pub fn parse_file<R>(path: impl AsRef<Path>, resolver: R) -> Object
+where
+    R: FnMut(&Path) -> io::Result<String>,
+{
+    parse_file_with(path, resolver, Lints::default()).0
+}
+
+pub fn parse_file_with<R>(
+    path: impl AsRef<Path>,
+    mut resolver: R,
+    lints: Lints,
+) -> (Object, Vec<String>)
+where
+    R: FnMut(&Path) -> io::Result<String>,
+{
+    let mut parser = Parser::new(&mut resolver, lints);
+
+    parser.include(path.as_ref());
+    let (result, warnings, _) = parser.finish(true).unwrap_or_else(|e| report(&e));
+    (result, warnings)
+}
+
+impl<'a> Parser<'a> {
+    fn new(resolver: &'a mut FileResolver<'a>, lints: Lints) -> Self {
+        Self {
+            result: Object::new(),
+            resolver,
+            includes: vec![],
+            subject: None,
+            prototypes: IndexMap::new(),
+            declared: IndexMap::new(),
+            section: None,
+            sections: IndexSet::new(),
+            definitions: HashMap::new(),
+            types: IndexMap::new(),
+            line: 1,
+            lints,
+            warnings: vec![],
+        }
+    }
+
+    // A strict finish reports every name that is never defined, otherwise they become words
+    fn finish(mut self, strict: bool) -> Result<(Object, Vec<String>, Kinds), Vec<Diagnostic>> {
+        // `fairy is an enemy` makes fairy an object of that kind rather than a copy
+        let mut kinds = vec![];
+        for (name, value) in self.result.iter() {
+            match value {
+                Value::Object(map) if self.sections.contains(name) => {
+                    for (k, v) in map {
+                        if matches!(v, Value::Reference(ty) if self.types.contains_key(ty)) {
+                            kinds.push(vec![name.clone(), k.clone()]);
+                        }
+                    }
+                }
+                Value::Reference(ty) if self.types.contains_key(ty) => {
+                    kinds.push(vec![name.clone()])
+                }
+                _ => {}
+            }
+        }
+
+        for path in kinds {
+            let value = match &mut self.result[&path[0]] {
+                Value::Object(map) if path.len() == 2 => &mut map[&path[1]],
+                value => value,
+            };
+
+            let Value::Reference(ty) = std::mem::replace(value, Value::Object(Object::new()))
+            else {
+                unreachable!();
+            };
+            self.prototypes.insert(path, ty);
+        }
+
+        let objs = std::mem::take(&mut self.result);
+        let result = resolve(
+            objs,
+            &self.prototypes,
+            &self.declared,
+            &self.sections,
+            &self.types,
+            strict,
+        )?;
+
+        let mismatches = self.check_types(&result);
+        if !mismatches.is_empty() {
+            return Err(mismatches);
+        }
+
+        let mut paths = vec![];
+        for (name, value) in &result {
+            match value {
+                Value::Object(map) if self.sections.contains(name) => {
+                    paths.extend(map.keys().map(|k| vec![name.clone(), k.clone()]));
+                }
+                _ => paths.push(vec![name.clone()]),
+            }
+        }
+
+        let kinds = paths
+            .into_iter()
+            .filter_map(|path| {
+                let kinds = self.kinds_of(&result, &path);
+                (kinds.len() > 1).then(|| (path.join("'s "), kinds[1..].to_vec()))
+            })
+            .collect();
+
+        Ok((result, self.warnings, kinds))
+    }
+
+    // Finds every property that doesn't hold what its object's kind declares
+    fn check_types(&self, result: &Object) -> Vec<Diagnostic> {
+        let mut objects = vec![];
+        for (name, value) in result {
+            match value {
+                Value::Object(map) if self.sections.contains(name) => {
+                    for (k, v) in map {
+                        objects.push((vec![name.clone(), k.clone()], v));
+                    }
+                }
+                v => objects.push((vec![name.clone()], v)),
+            }
+        }
+
+        let mut mismatches = vec![];
+        for (path, value) in objects {
+            let Value::Object(map) = value else {
+                continue;
+            };
+
+            let kinds = self.kinds_of(result, &path);
+            for (kind, fields) in self.types.iter().filter(|(ty, _)| kinds.contains(ty)) {
+                for (field, ty) in fields {
+                    let Some(value) = map.get(field) else {
+                        continue;
+                    };
+
+                    if !ty.matches(value) {
+                        let mut property = path.clone();
+                        property.push(field.clone());
+
+                        mismatches.push(Diagnostic::Mismatch {
+                            path: property,
+                            expected: ty.to_string(),
+                            kind: kind.clone(),
+                            found: value.clone(),
+                            location: self.location_of(result, &path, field),
+                        });
+                    }
+                }
+            }
+        }
+
+        mismatches
+    }
+
+    // The object's own name followed by the names of its prototypes
+    fn kinds_of(&self, result: &Object, path: &[String]) -> Vec<String> {
+        let mut kinds = vec![];
+        let mut path = Some(path.to_vec());
+
+        while let Some(current) = path {
+            if kinds.contains(current.last().unwrap()) {
+                break;
+            }
+
+            kinds.push(current.last().unwrap().clone());
+            let prototype = self.prototypes.get(&current);
+            path = prototype
+                .or(self.declared.get(&current))
+                .and_then(|prototype| {
+                    let section = current.first().filter(|s| self.sections.contains(*s));
+                    lookup(result, section, prototype).or_else(|| Some(vec![prototype.clone()]))
+                });
+        }
+
+        kinds
+    }
+
+    // Where `field` was set on the object at `path`, or on the prototype it came from
+    fn location_of(&self, result: &Object, path: &[String], field: &str) -> String {
+        let mut path = Some(path.to_vec());
+
+        while let Some(current) = path {
+            let mut property = current.clone();
+            property.push(field.to_string());
+
+            if let Some(definition) = self.definitions.get(&property) {
+                return definition.location.clone();
+            }
+
+            path = self.prototypes.get(&current).and_then(|prototype| {
+                let section = current.first().filter(|s| self.sections.contains(*s));
+                lookup(result, section, prototype).filter(|p| *p != current)
+            });
+        }
+
+        "an unknown place".to_string()
+    }
+
+    fn include(&mut self, path: &Path) {
+        let path = match self.includes.last().and_then(|file| file.parent()) {
+            Some(dir) => normalize(&dir.join(path)),
+            None => normalize(path),
+        };
+
+        if self.includes.contains(&path) {
+            let cycle: Vec<_> = self
+                .includes
+                .iter()
+                .skip_while(|file| **file != path)
+                .chain([&path])
+                .map(|file| file.display().to_string())
+                .collect();
+            panic!("Include cycle: {}", cycle.join(" -> "));
+        }
+
+        let text = (self.resolver)(&path)
+            .unwrap_or_else(|err| panic!("Failed to include {}: {err}", path.display()));
+
+        self.includes.push(path);
+        self.collect(&tokenize(&text));
+        self.includes.pop();
+    }
+
+    fn collect(&mut self, tokens: &[Token]) {
+        let mut line = 1;
+        let mut lines = vec![];
+        let tokens: Vec<_> = tokens
+            .iter()
+            .filter(|t| match t {
+                Token::Newline => {
+                    line += 1;
+                    false
+                }
+                _ => {
+                    lines.push(line);
+                    true
+                }
+            })
+            .cloned()
+            .collect();
+
+        let mut tokens = tokens.iter().peekable();
+        let base = self.section.clone();
+
+        while let Some(a) = tokens.next() {
+            self.line = lines[lines.len() - tokens.len() - 1];
+
+            match a {
+                // also! ...
+                Token::Keyword(k) if k == "also" => continue,
+                // add! values! to! target!
+                Token::Keyword(k) if k == "add" || k == "remove" => {
+                    let mut values = vec![];
+                    while let Some(value) =
+                        next_item(&mut tokens, |t| !matches!(t, Token::Keyword(_)))
+                    {
+                        values.push(parse_value(value, &mut tokens).unwrap_or_else(|| {
+                            panic!("Unexpected token pattern: ->{:?}<-", [a, value])
+                        }));
+                    }
+
+                    match tokens.next() {
+                        Some(Token::Keyword(to))
+                            if (k == "add" && to == "to") || (k == "remove" && to == "from") => {}
+                        d => panic!("Unexpected token pattern: ->{:?}<-", [Some(a), d]),
+                    }
+
+                    let (name, property) = parse_target(&mut tokens);
+                    let ls = self.list_mut(&name, &property);
+
+                    if k == "add" {
+                        ls.extend(values);
+                    } else {
+                        ls.retain(|v| !values.iter().any(|value| same_item(v, value)));
+                    }
+                }
+                // clear! target!
+                Token::Keyword(k) if k == "clear" => {
+                    let (name, property) = parse_target(&mut tokens);
+                    self.list_mut(&name, &property).clear();
+                }
+                // forget! target!
+                Token::Keyword(k) if k == "forget" => {
+                    let (name, property) = parse_target(&mut tokens);
+                    self.forget(&name, &property);
+                }
+                // in! idents! punct!
+                Token::Keyword(k) if k == "in" => {
+                    let mut words = vec![];
+                    while let Some(Token::Identifier(word)) = tokens.peek() {
+                        words.push(word.to_lowercase());
+                        tokens.next();
+                    }
+
+                    if words.is_empty() {
+                        panic!("Expected a section name after ->{a:?}<-");
+                    }
+                    if tokens
+                        .next_if(|t| matches!(t, Token::Punctuation(p) if p == ":"))
+                        .is_none()
+                    {
+                        panic!("Expected `:` after section name");
+                    }
+
+                    let section = words.join(" ");
+                    self.sections.insert(section.clone());
+                    self.section = Some(section);
+                    continue;
+                }
+                // include! str!
+                Token::Keyword(k) if k == "include" => match tokens.next() {
+                    Some(Token::String(path)) => self.include(Path::new(path)),
+                    d => panic!("Unexpected token pattern: ->{:?}<-", [Some(a), d]),
+                },
+                // pron! ...
+                Token::Pronoun(p) => {
+                    let Some(subject) = self.subject.clone() else {
+                        panic!("Nothing for ->{p}<- to refer to");
+                    };
+
+                    let name = Token::Identifier(subject);
+                    let s = Token::Possesive("s".to_string());
+                    let (b, c) = if POSSESSIVE_PRONOUNS.contains(&p.to_lowercase().as_str()) {
+                        (Some(&s), tokens.next())
+                    } else {
+                        (tokens.next(), tokens.next())
+                    };
+
+                    let (Some(b), Some(c)) = (b, c) else {
+                        panic!("Unexpected token pattern: ->{:?}<-", [a]);
+                    };
+                    self.sentence(&mut tokens, &name, b, c);
+                }
+                _ => {
+                    let (Some(b), Some(c)) = (tokens.next(), tokens.next()) else {
+                        panic!("Unexpected token pattern: ->{:?}<-", [a]);
+                    };
+                    self.sentence(&mut tokens, a, b, c);
+                }
+            }
+
+            if let Some(Token::Punctuation(p)) = tokens.peek() {
+                if p == "." {
+                    self.section = base.clone();
+                }
+                tokens.next();
+            }
+        }
 
-    while let (Some(a), Some(b), Some(c)) = (tokens.next(), tokens.next(), tokens.next()) {
+        self.section = base;
+    }
+
+    fn sentence(&mut self, tokens: &mut Tokens, a: &Token, b: &Token, c: &Token) {
         match (a, b, c) {
             // ident! kword! num!
             (Token::Identifier(name), Token::Keyword(k), Token::Number(value)) if k == "is" => {
-                let value = Value::Number(*value);
-                result.insert(name.to_lowercase(), value);
+                let value = parse_number(*value, tokens);
+                self.set_global(name, value);
             }
             // ident! kword! str!
             (Token::Identifier(name), Token::Keyword(k), Token::String(value)) if k == "is" => {
                 let value = Value::String(value.to_string());
-                result.insert(name.to_lowercase(), value);
+                self.set_global(name, value);
             }
             // ident! kword! ident!
             (Token::Identifier(name), Token::Keyword(k), Token::Identifier(var)) if k == "is" => {
-                let value = result.get(var).expect(&format!("Didn't find {var}"));
-                result.insert(name.to_lowercase(), value.clone());
+                let value = parse_word(var);
+                let declares = matches!(value, Value::Reference(_));
+                self.set_global(name, value);
+                self.subject = Some(name.to_lowercase());
+
+                // `fairy is an enemy` makes fairy an enemy, whether or not enemy is defined
+                if declares {
+                    let path = self.path(&name.to_lowercase());
+                    self.declared.insert(path, var.to_lowercase());
+                }
+            }
+            // ident! kword! time!
+            (Token::Identifier(name), Token::Keyword(k), Token::Time(_) | Token::Keyword(_))
+                if k == "is" && is_time(c) =>
+            {
+                let value = parse_value(c, tokens)
+                    .unwrap_or_else(|| panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]));
+                self.set_global(name, value);
+            }
+            // ident! kword! kword!
+            (Token::Identifier(name), Token::Keyword(k), Token::Keyword(always))
+                if k == "is" && always == "always" =>
+            {
+                let value = tokens
+                    .next()
+                    .and_then(|t| parse_value(t, tokens))
+                    .unwrap_or_else(|| panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]));
+
+                self.define(self.path(name), true);
+                self.scope().insert(name.to_lowercase(), value);
+            }
+            // ident! kword! kword!
+            (Token::Identifier(name), Token::Keyword(k), Token::Keyword(like))
+                if k == "is" && like == "like" =>
+            {
+                let Some(Token::Identifier(prototype)) = tokens.next() else {
+                    panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]);
+                };
+
+                let name = name.to_lowercase();
+                let scope = self.scope();
+                if !matches!(scope.get(&name), Some(Value::Object(_))) {
+                    scope.insert(name.clone(), Value::Object(Object::new()));
+                }
+
+                let path = self.path(&name);
+                self.declared.shift_remove(&path);
+                self.prototypes.insert(path, prototype.to_lowercase());
+                self.subject = Some(name);
             }
             // ident! poss! ident!
             (Token::Identifier(name), Token::Possesive(k), Token::Identifier(property))
                 if k == "s" =>
             {
                 let kword = tokens.next().unwrap();
+                let constant = tokens.next_if(|t| is_always(t)).is_some();
                 let value = match kword {
-                    Token::Keyword(k) if k == "is" => match tokens.next().unwrap() {
-                        Token::Number(value) => Value::Number(*value),
-                        Token::String(value) => Value::String(value.to_string()),
-                        Token::Identifier(var) => result
-                            .get(var)
-                            .expect(&format!("Didn't find {var}"))
-                            .clone(),
-                        d => panic!("Unexpected token pattern: ->{:?}<-", [a, b, c, kword, d]),
-                    },
+                    Token::Keyword(k) if k == "is" => {
+                        let d = tokens.next().unwrap();
+                        parse_value(d, tokens).unwrap_or_else(|| {
+                            panic!("Unexpected token pattern: ->{:?}<-", [a, b, c, kword, d])
+                        })
+                    }
                     Token::Keyword(k) if k == "are" => match tokens.next().unwrap() {
                         Token::Number(value) => {
-                            let mut ls = vec![Value::Number(*value)];
+                            let mut ls = vec![parse_number(*value, tokens)];
 
-                            while let Some(Token::Number(value)) = tokens.next() {
-                                ls.push(Value::Number(*value));
+                            while let Some(Token::Number(value)) =
+                                next_item(tokens, |t| matches!(t, Token::Number(_)))
+                            {
+                                ls.push(parse_number(*value, tokens));
                             }
 
                             Value::List(ls)
@@ -115,60 +743,746 @@ pub fn parse(tokens: Vec<Token>) -> Object {
                         Token::String(value) => {
                             let mut ls = vec![Value::String(value.to_string())];
 
-                            while let Some(Token::String(value)) = tokens.next() {
+                            while let Some(Token::String(value)) =
+                                next_item(tokens, |t| matches!(t, Token::String(_)))
+                            {
                                 ls.push(Value::String(value.to_string()));
                             }
 
                             Value::List(ls)
                         }
+                        Token::Identifier(var) => {
+                            let mut ls = vec![parse_word(var)];
+
+                            // A word that starts the next sentence, `marisa's ...`, isn't an item
+                            let mut ahead = tokens.clone();
+                            while let Some(Token::Identifier(var)) =
+                                next_item(&mut ahead, |t| matches!(t, Token::Identifier(_)))
+                            {
+                                if starts_sentence(ahead.peek().copied()) {
+                                    break;
+                                }
+
+                                ls.push(parse_word(var));
+                                *tokens = ahead.clone();
+                            }
+
+                            Value::List(ls)
+                        }
                         d => panic!("Unexpected token pattern: ->{:?}<-", [a, b, c, kword, d]),
                     },
                     d => panic!("Unexpected token pattern: ->{:?}<-", [a, b, c, d]),
                 };
 
-                set_obj_property!(result, name, property, value);
+                self.set_property(name, property, value, constant);
+            }
+            // ident! poss! type!
+            (Token::Identifier(name), Token::Possesive(k), Token::Identifier(_))
+                if (k == "has" || k == "have") && starts_type(c, tokens.peek().copied()) =>
+            {
+                let mut fields = vec![parse_field(c, tokens)];
+
+                loop {
+                    let mut ahead = tokens.clone();
+                    ahead.next_if(|t| matches!(t, Token::Punctuation(p) if p == ","));
+
+                    match ahead.next() {
+                        Some(ty) if starts_type(ty, ahead.peek().copied()) => {
+                            fields.push(parse_field(ty, &mut ahead));
+                            *tokens = ahead;
+                        }
+                        _ => break,
+                    }
+                }
+
+                let kind = self.types.entry(name.to_lowercase()).or_default();
+                kind.extend(fields);
             }
             // ident! poss! ident!
             (Token::Identifier(name), Token::Possesive(k), Token::Identifier(property))
                 if k == "has" || k == "have" =>
             {
-                let value = match tokens.next().unwrap() {
-                    Token::Number(value) => Value::Number(*value),
-                    Token::String(value) => Value::String(value.to_string()),
-                    Token::Identifier(var) => result
-                        .get(&var.to_lowercase())
-                        .expect(&format!("Didn't find {var}"))
-                        .clone(),
-                    _ => panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]),
+                let value = tokens
+                    .next()
+                    .and_then(|t| parse_value(t, tokens))
+                    .unwrap_or_else(|| panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]));
+
+                self.set_property(name, property, value, false);
+            }
+            // ident! poss! num!
+            (Token::Identifier(name), Token::Possesive(k), Token::Number(value))
+                if k == "has" || k == "have" =>
+            {
+                let (value, property) = match (tokens.next(), tokens.peek()) {
+                    // `3 seconds cooldown`, unless the unit is the property itself as in `30 frames`
+                    (Some(Token::Identifier(unit)), Some(Token::Identifier(property)))
+                        if Unit::from_word(unit).is_some() =>
+                    {
+                        tokens.next();
+                        let unit = Unit::from_word(unit).unwrap();
+                        (Value::Quantity(*value, unit), property)
+                    }
+                    // `200 health`
+                    (Some(Token::Identifier(property)), _) => (Value::Number(*value), property),
+                    (d, _) => panic!(
+                        "Unexpected token pattern: ->{:?}<-",
+                        [Some(a), Some(b), Some(c), d]
+                    ),
+                };
+
+                self.set_property(name, property, value, false);
+            }
+            // ident! poss! kword!
+            (Token::Identifier(name), Token::Possesive(k), Token::Keyword(no))
+                if no == "no" && (k == "has" || k == "have") =>
+            {
+                let Some(Token::Identifier(property)) = tokens.next() else {
+                    panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]);
+                };
+
+                self.forget(name, &Some(property.to_lowercase()));
+            }
+            // ident! also! poss!
+            (Token::Identifier(name), Token::Keyword(also), Token::Possesive(k))
+                if also == "also" && (k == "has" || k == "have") =>
+            {
+                let Some(Token::Identifier(property)) = tokens.next() else {
+                    panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]);
+                };
+
+                let value = tokens
+                    .next()
+                    .and_then(|t| parse_value(t, tokens))
+                    .unwrap_or_else(|| panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]));
+
+                let property = property.to_lowercase();
+                let property = match self.scope().get(&name.to_lowercase()) {
+                    Some(Value::Object(map))
+                        if matches!(map.get(&property), Some(Value::List(_))) =>
+                    {
+                        property
+                    }
+                    _ => format!("{property}s"),
                 };
 
-                set_obj_property!(result, name, property, value);
+                self.list_mut(name, &Some(property)).push(value);
             }
             // ident! poss! ident!
             (Token::Identifier(property), Token::Possesive(k), Token::Identifier(name))
                 if k == "of" =>
             {
-                let value = match (tokens.next().unwrap(), tokens.next().unwrap()) {
-                    (Token::Keyword(k), Token::Number(value)) if k == "is" => Value::Number(*value),
-                    (Token::Keyword(k), Token::String(value)) if k == "is" => {
-                        Value::String(value.to_string())
-                    }
-                    (Token::Keyword(k), Token::Identifier(var)) if k == "is" => result
-                        .get(var)
-                        .expect(format!("Didn't find {var}").as_str())
-                        .clone(),
+                let kword = tokens.next().unwrap();
+                let constant = tokens.next_if(|t| is_always(t)).is_some();
+                let value = match (kword, tokens.next().unwrap()) {
+                    (Token::Keyword(k), e) if k == "is" => {
+                        parse_value(e, tokens).unwrap_or_else(|| {
+                            panic!("Unexpected token pattern: ->{:?}<-", [a, b, c, kword, e])
+                        })
+                    }
                     (d, e) => panic!("Unexpected token pattern: ->{:?}<-", [a, b, c, d, e]),
                 };
 
-                set_obj_property!(result, name, property, value);
+                self.set_property(name, property, value, constant);
             }
             _ => panic!("Unexpected token pattern: ->{:?}<-", [a, b, c]),
         }
+    }
+
+    // The object sentences are currently written into, either a section or the globals
+    fn scope(&mut self) -> &mut Object {
+        match &self.section {
+            Some(section) => match self
+                .result
+                .entry(section.clone())
+                .or_insert_with(|| Value::Object(Object::new()))
+            {
+                Value::Object(map) => map,
+                tt => panic!("Expected {section} to be a section but found {tt:?}!"),
+            },
+            None => &mut self.result,
+        }
+    }
+
+    fn path(&self, name: &str) -> Vec<String> {
+        self.section
+            .iter()
+            .cloned()
+            .chain([name.to_string()])
+            .collect()
+    }
+
+    fn location(&self) -> String {
+        match self.includes.last() {
+            Some(file) => format!("{}:{}", file.display(), self.line),
+            None => format!("line {}", self.line),
+        }
+    }
+
+    // Fails if `path` or anything containing it was declared with `always`
+    fn check_constant(&self, path: &[String]) {
+        for end in 1..=path.len() {
+            if let Some(definition) = self.definitions.get(&path[..end]) {
+                if definition.constant {
+                    panic!(
+                        "Cannot change constant `{}` at {}, it is always what was set at {}",
+                        path[..end].join("'s "),
+                        self.location(),
+                        definition.location
+                    );
+                }
+            }
+        }
+    }
+
+    fn define(&mut self, path: Vec<String>, constant: bool) {
+        self.check_constant(&path);
+
+        let location = self.location();
+        if let Some(previous) = self.definitions.get(&path) {
+            if self.lints.redefinitions {
+                self.warnings.push(format!(
+                    "`{}` is redefined at {location}, it was previously set at {}",
+                    path.join("'s "),
+                    previous.location
+                ));
+            }
+        }
+
+        self.definitions
+            .insert(path, Definition { location, constant });
+    }
+
+    fn set_global(&mut self, name: &str, value: Value) {
+        let name = name.to_lowercase();
+        let path = self.path(&name);
+        self.define(path.clone(), false);
+        self.prototypes.shift_remove(&path);
+        self.declared.shift_remove(&path);
+        self.scope().insert(name, value);
+    }
 
-        if let Some(Token::Punctuation(_)) = tokens.peek() {
+    fn set_property(&mut self, name: &str, property: &str, value: Value, constant: bool) {
+        let mut path = self.path(&name.to_lowercase());
+
+        // `fairy is an enemy, its health is 10` makes fairy an enemy with its own health
+        if let Some(Value::Reference(prototype)) = self.scope().get(&name.to_lowercase()) {
+            let prototype = prototype.clone();
+            self.scope()
+                .insert(name.to_lowercase(), Value::Object(Object::new()));
+            self.prototypes.insert(path.clone(), prototype);
+        }
+
+        path.push(property.to_lowercase());
+        self.define(path, constant);
+
+        let result = self.scope();
+        set_obj_property!(result, name, property, value);
+        self.subject = Some(name.to_lowercase());
+    }
+
+    fn forget(&mut self, name: &str, property: &Option<String>) {
+        let name = name.to_lowercase();
+        let mut path = self.path(&name);
+        path.extend(property.clone());
+
+        self.check_constant(&path);
+        self.definitions.retain(|p, _| !p.starts_with(&path));
+
+        match property {
+            Some(property) => match self.scope().get_mut(&name) {
+                Some(Value::Object(map)) => {
+                    map.shift_remove(property);
+                }
+                Some(tt) => panic!("Expected {name} to be a object but found {tt:?}!"),
+                None => {}
+            },
+            None => {
+                let path = self.path(&name);
+                self.scope().shift_remove(&name);
+                self.prototypes.shift_remove(&path);
+                self.declared.shift_remove(&path);
+            }
+        }
+    }
+
+    fn list_mut(&mut self, name: &str, property: &Option<String>) -> &mut Vec<Value> {
+        let name = name.to_lowercase();
+        if property.is_some() {
+            self.subject = Some(name.clone());
+        }
+
+        let mut path = self.path(&name);
+        path.extend(property.clone());
+        self.check_constant(&path);
+
+        let scope = self.scope();
+        let (value, path) = match property {
+            Some(property) => {
+                let obj = scope
+                    .entry(name.clone())
+                    .or_insert_with(|| Value::Object(Object::new()));
+
+                let Value::Object(map) = obj else {
+                    panic!("Expected {name} to be a object but found {obj:?}!");
+                };
+
+                (
+                    map.entry(property.clone()).or_insert(Value::List(vec![])),
+                    format!("{name}'s {property}"),
+                )
+            }
+            None => (
+                scope.entry(name.clone()).or_insert(Value::List(vec![])),
+                name,
+            ),
+        };
+
+        match value {
+            Value::List(ls) => ls,
+            tt => panic!("Expected {path} to be a list but found {tt:?}!"),
+        }
+    }
+}
+
+// Folds `.` and `..` so the same file is always seen under the same path
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normal.file_name().is_some() => {
+                normal.pop();
+            }
+            c => normal.push(c),
+        }
+    }
+
+    normal
+}
+
+fn type_of(word: &str) -> Option<Type> {
+    match word.to_lowercase().as_str() {
+        "number" | "numbers" => Some(Type::Number),
+        "word" | "words" | "string" | "strings" | "text" => Some(Type::Word),
+        _ => None,
+    }
+}
+
+// Whether `token` starts a declaration like `number called health` or `list of words called items`
+fn starts_type(token: &Token, next: Option<&Token>) -> bool {
+    match (token, next) {
+        (Token::Identifier(word), Some(Token::Keyword(k))) => {
+            k == "called" && type_of(word).is_some()
+        }
+        (Token::Identifier(word), Some(Token::Possesive(k))) => {
+            k == "of" && word.to_lowercase() == "list"
+        }
+        _ => false,
+    }
+}
+
+fn parse_type(token: &Token, tokens: &mut Tokens) -> Type {
+    match token {
+        Token::Identifier(word) if word.to_lowercase() == "list" => {
+            match (tokens.next(), tokens.next()) {
+                (Some(Token::Possesive(k)), Some(ty)) if k == "of" => {
+                    Type::List(Box::new(parse_type(ty, tokens)))
+                }
+                (d, e) => panic!("Unexpected token pattern: ->{:?}<-", [Some(token), d, e]),
+            }
+        }
+        Token::Identifier(word) => {
+            type_of(word).unwrap_or_else(|| panic!("Unknown type ->{word}<-"))
+        }
+        d => panic!("Unexpected token pattern: ->{:?}<-", [d]),
+    }
+}
+
+fn parse_field(token: &Token, tokens: &mut Tokens) -> (String, Type) {
+    let ty = parse_type(token, tokens);
+
+    match (tokens.next(), tokens.next()) {
+        (Some(Token::Keyword(k)), Some(Token::Identifier(field))) if k == "called" => {
+            (field.to_lowercase(), ty)
+        }
+        (d, e) => panic!("Unexpected token pattern: ->{:?}<-", [Some(token), d, e]),
+    }
+}
+
+// Names are looked up in the section they're used in first, then in the globals
+fn lookup(objs: &Object, section: Option<&String>, name: &str) -> Option<Vec<String>> {
+    if let Some(section) = section {
+        if let Some(Value::Object(map)) = objs.get(section) {
+            if map.contains_key(name) {
+                return Some(vec![section.clone(), name.to_string()]);
+            }
+        }
+    }
+
+    objs.contains_key(name).then(|| vec![name.to_string()])
+}
+
+fn starts_sentence(next: Option<&Token>) -> bool {
+    match next {
+        Some(Token::Possesive(_)) => true,
+        Some(Token::Keyword(k)) => k == "is" || k == "are",
+        _ => false,
+    }
+}
+
+fn is_always(token: &Token) -> bool {
+    matches!(token, Token::Keyword(k) if k == "always")
+}
+
+// Takes the next list item, which may be separated from the previous one by a comma
+fn next_item<'a>(tokens: &mut Tokens<'a>, condition: impl Fn(&Token) -> bool) -> Option<&'a Token> {
+    let mut ahead = tokens.clone();
+    ahead.next_if(|t| matches!(t, Token::Punctuation(p) if p == ","));
+
+    let item = ahead.next_if(|t| condition(t))?;
+    *tokens = ahead;
+    Some(item)
+}
+
+// Whether `token` starts a time, `1:30`, `at 1:30`, `after 3 seconds` or `every half a second`
+fn is_time(token: &Token) -> bool {
+    match token {
+        Token::Time(_) => true,
+        Token::Keyword(k) => k == "at" || k == "after" || k == "every",
+        _ => false,
+    }
+}
+
+fn parse_value(token: &Token, tokens: &mut Tokens) -> Option<Value> {
+    match token {
+        Token::Number(value) => Some(parse_number(*value, tokens)),
+        Token::Time(seconds) => Some(Value::Quantity(*seconds, Unit::Seconds)),
+        Token::Keyword(_) if is_time(token) => parse_time(tokens),
+        Token::String(value) => Some(Value::String(value.to_string())),
+        Token::Identifier(var) => Some(parse_word(var)),
+        _ => None,
+    }
+}
+
+fn parse_word(var: &str) -> Value {
+    match var.to_lowercase().as_str() {
+        "nothing" | "none" => Value::Nothing,
+        // Read by `bool` fields
+        "true" | "false" | "yes" => Value::Word(var.to_lowercase()),
+        var => Value::Reference(var.to_string()),
+    }
+}
+
+// A number followed by a unit, `2 seconds`, is a quantity of that unit
+fn parse_number(value: f32, tokens: &mut Tokens) -> Value {
+    let unit = match tokens.peek() {
+        Some(Token::Identifier(word)) => Unit::from_word(word),
+        _ => None,
+    };
+
+    match unit {
+        Some(unit) => {
             tokens.next();
+            Value::Quantity(value, unit)
+        }
+        None => Value::Number(value),
+    }
+}
+
+// Reads what follows `at`, `after` or `every`, plain numbers being seconds. Frames are kept
+// as they are, for a number field or a type that knows the frame rate rather than a `Duration`
+fn parse_time(tokens: &mut Tokens) -> Option<Value> {
+    let (amount, unit) = match tokens.next()? {
+        Token::Time(seconds) => return Some(Value::Quantity(*seconds, Unit::Seconds)),
+        Token::Number(value) => match parse_number(*value, tokens) {
+            Value::Quantity(value, unit) => (value, unit),
+            _ => (*value, Unit::Seconds),
+        },
+        Token::Identifier(word) => {
+            let amount = match word.to_lowercase().as_str() {
+                "half" => Some(0.5),
+                "quarter" => Some(0.25),
+                _ => None,
+            };
+
+            // `every second` is one of them
+            let word = match amount {
+                Some(_) => match tokens.next()? {
+                    Token::Identifier(word) => word,
+                    _ => return None,
+                },
+                None => word,
+            };
+
+            (amount.unwrap_or(1.0), Unit::from_word(word)?)
+        }
+        _ => return None,
+    };
+
+    unit.is_time().then_some(Value::Quantity(amount, unit))
+}
+
+// Bare words match the strings they spell, so `remove bow from ...` finds "bow"
+fn same_item(item: &Value, value: &Value) -> bool {
+    match (item, value) {
+        (Value::String(s), Value::Reference(var)) => s.to_lowercase() == *var,
+        _ => item == value,
+    }
+}
+
+// Reads `reimu's items`, `items of reimu` or just `items`
+fn parse_target(tokens: &mut Tokens) -> (String, Option<String>) {
+    match (tokens.next(), tokens.peek()) {
+        (Some(Token::Identifier(name)), Some(Token::Possesive(k))) if k == "s" || k == "of" => {
+            let k = k.clone();
+            tokens.next();
+
+            let Some(Token::Identifier(other)) = tokens.next() else {
+                panic!("Expected a property after ->{name}<-");
+            };
+
+            if k == "s" {
+                (name.to_lowercase(), Some(other.to_lowercase()))
+            } else {
+                (other.to_lowercase(), Some(name.to_lowercase()))
+            }
+        }
+        (Some(Token::Identifier(name)), _) => (name.to_lowercase(), None),
+        (d, _) => panic!("Unexpected token pattern: ->{:?}<-", [d]),
+    }
+}
+
+// Replaces every reference with the final value of the name it points to,
+// so declaration order doesn't matter and later edits are seen by the referrer
+fn resolve(
+    objs: Object,
+    prototypes: &IndexMap<Vec<String>, String>,
+    declared: &IndexMap<Vec<String>, String>,
+    sections: &IndexSet<String>,
+    types: &IndexMap<String, IndexMap<String, Type>>,
+    strict: bool,
+) -> Result<Object, Vec<Diagnostic>> {
+    let mut resolution = Resolution {
+        objs: &objs,
+        prototypes,
+        declared,
+        sections,
+        types,
+        resolved: HashMap::new(),
+        stack: vec![],
+    };
+
+    let undefined = resolution.undefined_prototypes();
+    if !undefined.is_empty() {
+        panic!("{}", undefined.join("\n"));
+    }
+
+    let undefined = if strict {
+        resolution.undefined()
+    } else {
+        vec![]
+    };
+    if !undefined.is_empty() {
+        return Err(undefined);
+    }
+
+    Ok(objs
+        .keys()
+        .map(|name| (name.clone(), resolution.path(std::slice::from_ref(name))))
+        .collect())
+}
+
+struct Resolution<'a> {
+    objs: &'a Object,
+    prototypes: &'a IndexMap<Vec<String>, String>,
+    declared: &'a IndexMap<Vec<String>, String>,
+    sections: &'a IndexSet<String>,
+    types: &'a IndexMap<String, IndexMap<String, Type>>,
+    resolved: HashMap<Vec<String>, Value>,
+    stack: Vec<String>,
+}
+
+impl Resolution<'_> {
+    fn lookup(&self, section: Option<&String>, name: &str) -> Option<Vec<String>> {
+        lookup(self.objs, section, name)
+    }
+
+    fn section_of<'p>(&self, path: &'p [String]) -> Option<&'p String> {
+        path.first().filter(|name| self.sections.contains(*name))
+    }
+
+    fn undefined(&self) -> Vec<Diagnostic> {
+        let mut undefined = vec![];
+
+        for (name, value) in self.objs {
+            match value {
+                Value::Object(map) if self.sections.contains(name) => {
+                    for (k, v) in map {
+                        let path = [name.clone(), k.clone()];
+                        self.undefined_names(&path, v, Some(name), &mut undefined);
+                    }
+                }
+                v => self.undefined_names(std::slice::from_ref(name), v, None, &mut undefined),
+            }
+        }
+
+        undefined
+    }
+
+    fn undefined_prototypes(&self) -> Vec<String> {
+        let mut undefined = vec![];
+
+        for (path, prototype) in self.prototypes {
+            // A kind needs no definition, `fairy is an enemy, its health is 10` is enough
+            let found = self.lookup(self.section_of(path), prototype).is_some();
+            if !found && !self.types.contains_key(prototype) && !self.declared.contains_key(path) {
+                let name = path.join("'s ");
+                undefined.push(format!(
+                    "Undefined name `{prototype}` in {name} is like {prototype}"
+                ));
+            }
+        }
+
+        undefined
+    }
+
+    fn undefined_names(
+        &self,
+        path: &[String],
+        value: &Value,
+        section: Option<&String>,
+        undefined: &mut Vec<Diagnostic>,
+    ) {
+        match value {
+            Value::Reference(var)
+                if self.lookup(section, var).is_none() && !self.declared.contains_key(path) =>
+            {
+                undefined.push(Diagnostic::Undefined {
+                    path: path.to_vec(),
+                    name: var.clone(),
+                });
+            }
+            Value::List(ls) => {
+                for v in ls {
+                    self.undefined_names(path, v, section, undefined);
+                }
+            }
+            Value::Object(map) => {
+                for (k, v) in map {
+                    // Words like `left` name nothing on purpose where the kind expects words
+                    if self.holds_words(path, k) {
+                        continue;
+                    }
+
+                    let mut property = path.to_vec();
+                    property.push(k.clone());
+                    self.undefined_names(&property, v, section, undefined);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Whether the object at `path` or one of its prototypes declares `property` to hold words
+    fn holds_words(&self, path: &[String], property: &str) -> bool {
+        let mut kinds = vec![];
+        let mut path = Some(path.to_vec());
+
+        while let Some(current) = path {
+            let kind = current.last().unwrap();
+            if kinds.contains(kind) {
+                break;
+            }
+
+            if let Some(ty) = self.types.get(kind).and_then(|fields| fields.get(property)) {
+                return ty.holds_words();
+            }
+
+            kinds.push(kind.clone());
+            let prototype = self.prototypes.get(&current);
+            path = prototype.or(self.declared.get(&current)).map(|prototype| {
+                self.lookup(self.section_of(&current), prototype)
+                    .unwrap_or_else(|| vec![prototype.clone()])
+            });
         }
+
+        false
     }
 
-    result
+    fn path(&mut self, path: &[String]) -> Value {
+        if let Some(value) = self.resolved.get(path) {
+            return value.clone();
+        }
+
+        let name = path.join("'s ");
+        if self.stack.contains(&name) {
+            self.stack.push(name);
+            panic!("Cyclic reference: {}", self.stack.join(" -> "));
+        }
+
+        let objs = self.objs;
+        let value = match path {
+            [name] => &objs[name],
+            [section, name] => match &objs[section] {
+                Value::Object(map) => &map[name],
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        self.stack.push(name);
+        let section = self.section_of(path);
+
+        let value = match (value, self.prototypes.get(path)) {
+            (Value::Object(map), None) if path.len() == 1 && section.is_some() => Value::Object(
+                map.keys()
+                    .map(|k| (k.clone(), self.path(&[path[0].clone(), k.clone()])))
+                    .collect(),
+            ),
+            (Value::Object(own), Some(prototype)) => {
+                // Kinds that are only declared as a type have no properties to copy
+                let base = match self.lookup(section, prototype) {
+                    Some(prototype) => self.path(&prototype),
+                    None => Value::Object(Object::new()),
+                };
+
+                let Value::Object(mut map) = base else {
+                    let prototype = self.lookup(section, prototype).unwrap();
+                    let (name, prototype) = (path.join("'s "), prototype.join("'s "));
+                    panic!("Expected {prototype} to be a object for {name} to be like it!");
+                };
+
+                for (property, value) in own {
+                    let value = self.value(value, section);
+                    map.insert(property.clone(), value);
+                }
+
+                Value::Object(map)
+            }
+            (value, _) => self.value(value, section),
+        };
+
+        self.stack.pop();
+
+        self.resolved.insert(path.to_vec(), value.clone());
+        value
+    }
+
+    fn value(&mut self, value: &Value, section: Option<&String>) -> Value {
+        match value {
+            // Only words declared by the kind get here, for the evaluator to read as enum variants
+            Value::Reference(var) => match self.lookup(section, var) {
+                Some(path) => self.path(&path),
+                None => Value::Word(var.clone()),
+            },
+            Value::List(ls) => Value::List(ls.iter().map(|v| self.value(v, section)).collect()),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), self.value(v, section)))
+                    .collect(),
+            ),
+            v => v.clone(),
+        }
+    }
 }
@@ -1,3 +1,5 @@
+use std::iter;
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token {
     String(String),
@@ -6,7 +8,11 @@ pub enum Token {
     Possesive(String),
     Identifier(String),
     Keyword(String),
+    Pronoun(String),
     Number(f32),
+    // A time like `1:30`, in seconds
+    Time(f32),
+    Newline,
     None,
 }
 
@@ -19,6 +25,13 @@ macro_rules! token_macro {
             };
         }
     };
+    ($id: ident, Time) => {
+        macro_rules! $id {
+            ($name: literal) => {
+                Token::Time($name)
+            };
+        }
+    };
     ($id: ident, $token: ident) => {
         macro_rules! $id {
             ($name: literal) => {
@@ -28,10 +41,14 @@ macro_rules! token_macro {
     };
 }
 
-const PREPOSITIONS: [&str; 5] = ["the", "a", "an", "and", "also"];
-const KEYWORDS: [&str; 2] = ["is", "are"];
-const PUNCTUATION: [&str; 2] = [".", ","];
+const PREPOSITIONS: [&str; 5] = ["the", "a", "an", "and", "but"];
+const KEYWORDS: [&str; 18] = [
+    "is", "are", "also", "add", "remove", "clear", "to", "from", "forget", "no", "include", "like",
+    "in", "always", "called", "at", "after", "every",
+];
+const PUNCTUATION: [&str; 3] = [".", ",", ":"];
 const POSSESIVES: [&str; 4] = ["s", "of", "has", "have"];
+const PRONOUNS: [&str; 8] = ["it", "its", "she", "her", "he", "his", "they", "their"];
 
 macro_rules! contains {
     ($list: ident, $word: ident) => {
@@ -41,17 +58,13 @@ macro_rules! contains {
 
 pub fn tokenize(text: &str) -> Vec<Token> {
     let mut tokens = vec![];
-    let mut chars = text
-        .trim()
-        .chars()
-        .skip_while(|c| c.is_whitespace())
-        .peekable();
+    let mut chars = text.trim_end().chars().peekable();
 
     while let Some(c) = chars.peek() {
         let token = match c {
             'a'..='z' | 'A'..='Z' | '_' => {
                 let condition = |c: &char| c.is_alphanumeric() || *c == '_';
-                let word = String::from_iter(chars.by_ref().take_while(condition));
+                let word = String::from_iter(iter::from_fn(|| chars.next_if(condition)));
 
                 if contains!(PREPOSITIONS, word) {
                     Token::Preposition(word)
@@ -59,19 +72,57 @@ pub fn tokenize(text: &str) -> Vec<Token> {
                     Token::Keyword(word)
                 } else if contains!(POSSESIVES, word) {
                     Token::Possesive(word)
+                } else if contains!(PRONOUNS, word) {
+                    Token::Pronoun(word)
                 } else {
                     Token::Identifier(word)
                 }
             }
             '0'..='9' => {
-                let condition = |c: &char| c.is_digit(10) || *c == '.';
-                let number = String::from_iter(chars.by_ref().take_while(condition));
-                Token::Number(number.parse().expect("Correct number format"))
+                let condition = |c: &char| c.is_ascii_digit();
+                let mut number = String::from_iter(iter::from_fn(|| chars.next_if(condition)));
+
+                // A dot only belongs to the number if digits follow, otherwise it ends the sentence
+                let mut ahead = chars.clone();
+                if ahead.next() == Some('.') && ahead.peek().is_some_and(condition) {
+                    chars.next();
+                    number.push('.');
+                    number.extend(iter::from_fn(|| chars.next_if(condition)));
+                }
+
+                // `1:30` is minutes and seconds, `1:02:30` hours, minutes and seconds
+                let mut time = None;
+                loop {
+                    let mut ahead = chars.clone();
+                    if ahead.next() != Some(':') || !ahead.peek().is_some_and(condition) {
+                        break;
+                    }
+
+                    chars.next();
+                    let part = String::from_iter(iter::from_fn(|| chars.next_if(condition)));
+                    let seconds =
+                        time.unwrap_or_else(|| number.parse().expect("Correct time format"));
+                    time = Some(seconds * 60.0 + part.parse::<f32>().expect("Correct time format"));
+                }
+
+                match time {
+                    Some(seconds) => Token::Time(seconds),
+                    None => Token::Number(number.parse().expect("Correct number format")),
+                }
             }
             '"' => {
                 chars.next();
                 let string = String::from_iter(chars.by_ref().take_while(|c| *c != '"'));
-                Token::String(string)
+                let lines = string.matches('\n').count();
+
+                tokens.push(Token::String(string));
+                tokens.extend(iter::repeat_n(Token::Newline, lines));
+                continue;
+            }
+            // Read like a word so `50%` is a number with a unit
+            '%' => {
+                chars.next();
+                Token::Identifier("%".to_string())
             }
             c if c.is_whitespace() || c.is_ascii_punctuation() => {
                 let c = c.to_string();
@@ -79,6 +130,8 @@ pub fn tokenize(text: &str) -> Vec<Token> {
 
                 if contains!(PUNCTUATION, c) {
                     Token::Punctuation(c)
+                } else if c == "\n" {
+                    Token::Newline
                 } else {
                     Token::None
                 }
@@ -88,11 +141,8 @@ pub fn tokenize(text: &str) -> Vec<Token> {
         tokens.push(token);
     }
 
-    return tokens
+    tokens
         .into_iter()
-        .filter(|t| match t {
-            Token::None | Token::Preposition(_) => false,
-            _ => true,
-        })
-        .collect();
+        .filter(|t| !matches!(t, Token::None | Token::Preposition(_)))
+        .collect()
 }
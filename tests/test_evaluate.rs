@@ -1,8 +1,14 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, num::NonZeroU32};
 
-use touhoulang::{evaluate, val_list, val_num, val_obj, val_str};
+use touhoulang::{
+    evaluate,
+    evaluator::{ErrorKind, EvalError, Evaluate, FromValue},
+    parser::{Object, Value},
+    val_list, val_num, val_obj, val_str,
+};
 
 evaluate! {
+    #[touhou(root)]
     struct Globals {
         text: String,
         number: i32,
@@ -10,6 +16,7 @@ evaluate! {
 }
 
 evaluate! {
+    #[derive(Default)]
     struct Health {
         current: u32,
         max: u32,
@@ -25,9 +32,24 @@ evaluate! {
     }
 }
 
+evaluate! {
+    struct Marisa {
+        name: std::string::String,
+        spells: HashMap<String, Vec<i32>>,
+    }
+}
+
+evaluate! {
+    #[derive(Debug)]
+    struct Bomb {
+        count: NonZeroU32,
+        name: String,
+    }
+}
+
 #[test]
 fn evaluates_globals() {
-    let objs = HashMap::from([val_str!("text", "hi mom!"), val_num!("number", 69.0)]);
+    let objs = Object::from([val_str!("text", "hi mom!"), val_num!("number", 69.0)]);
 
     let mut res = Globals {
         text: String::new(),
@@ -42,7 +64,7 @@ fn evaluates_globals() {
 
 #[test]
 fn evaluates_objects() {
-    let objs = HashMap::from([val_obj!(
+    let objs = Object::from([val_obj!(
         "reimu",
         val_obj!("health", val_num!("current", 100.0), val_num!("max", 100.0)),
         val_num!("age", 17.0),
@@ -64,3 +86,119 @@ fn evaluates_objects() {
     assert_eq!(res.health.current, 100);
     assert_eq!(res.health.max, 100);
 }
+
+#[test]
+fn evaluates_paths_and_generic_types() {
+    let objs = Object::from([val_obj!(
+        "marisa",
+        val_str!("name", "Marisa"),
+        val_obj!("spells", val_list!("spark", Number, 1., 2.))
+    )]);
+
+    let mut res = Marisa {
+        name: String::new(),
+        spells: HashMap::new(),
+    };
+    res.evaluate(objs);
+
+    assert_eq!(res.name, "Marisa");
+    assert_eq!(
+        res.spells,
+        HashMap::from([("spark".to_string(), vec![1, 2])])
+    );
+}
+
+#[test]
+fn reports_values_that_do_not_fit() {
+    assert_eq!(
+        i32::from_value(Value::String("abc".to_string())),
+        Err(ErrorKind::Mismatch {
+            expected: "i32".to_string(),
+            found: Value::String("abc".to_string()),
+        }
+        .into())
+    );
+    assert_eq!(
+        Vec::<String>::from_value(Value::Number(1.0))
+            .unwrap_err()
+            .to_string(),
+        "Expected Vec<String> but found Number(1.0)"
+    );
+    assert_eq!(
+        u8::from_value(Value::Number(300.0)),
+        Err(EvalError::mismatch::<u8>(Value::Number(300.0)))
+    );
+    assert_eq!(
+        u8::from_value(Value::Number(1.9)),
+        Err(EvalError::mismatch::<u8>(Value::Number(1.9)))
+    );
+    assert_eq!(
+        u32::from_value(Value::Number(-1.0)).unwrap_err().to_string(),
+        "Expected u32 but found Number(-1.0)"
+    );
+    assert_eq!(i8::from_value(Value::Number(-128.0)), Ok(-128));
+    assert_eq!(f32::from_value(Value::Number(1.9)), Ok(1.9));
+}
+
+#[test]
+fn reports_the_path_of_bad_fields() {
+    let objs = Object::from([val_obj!(
+        "reimu",
+        (
+            "numbers".to_string(),
+            Value::List(vec![
+                Value::Number(1.0),
+                Value::Number(2.0),
+                Value::String("abc".to_string()),
+            ])
+        )
+    )]);
+
+    let mut res = Reimu {
+        age: 0,
+        items: Vec::new(),
+        numbers: Vec::new(),
+        health: Health::default(),
+    };
+
+    assert_eq!(
+        res.try_evaluate(objs),
+        Err(EvalError {
+            path: "reimu.numbers[2]".to_string(),
+            kind: ErrorKind::Mismatch {
+                expected: "i32".to_string(),
+                found: Value::String("abc".to_string()),
+            },
+        })
+    );
+}
+
+#[test]
+fn reports_errors_from_text() {
+    let err = Reimu::try_from_str(
+        r#"the hp's current is "full", and the hp's max is 100.
+        reimu's health is hp."#,
+    )
+    .err()
+    .unwrap();
+
+    assert_eq!(
+        err.to_string(),
+        "reimu.health.current: Expected u32 but found String(\"full\")"
+    );
+}
+
+#[test]
+fn builds_objects_without_default() {
+    let bomb = Bomb::from_text("the bomb's count is 3");
+
+    assert_eq!(bomb.count.get(), 3);
+    assert_eq!(bomb.name, "");
+
+    let err = Bomb::try_from_str(r#"the bomb's name is "Fantasy Seal""#).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "bomb.count: Missing required property `count`"
+    );
+}
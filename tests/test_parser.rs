@@ -1,12 +1,19 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io, path::Path};
 
-use touhoulang::{parser::*, token_macro, tokenizer::Token, val_list, val_num, val_obj, val_str};
+use touhoulang::{
+    parser::*,
+    token_macro,
+    tokenizer::{tokenize, Token},
+    val_list, val_num, val_obj, val_str,
+};
 
 token_macro!(ident, Identifier);
 token_macro!(num, Number);
 token_macro!(kword, Keyword);
 token_macro!(poss, Possesive);
 token_macro!(str, String);
+token_macro!(pron, Pronoun);
+token_macro!(punct, Punctuation);
 
 fn expect<const N: usize, const M: usize>(tokens: [Token; N], result: [(String, Value); M]) {
     let result = HashMap::from(result);
@@ -231,3 +238,676 @@ fn parses_object_attribute() {
         [val_obj!("marisa", val_obj!("health", val_num!("max", 50.0)))],
     );
 }
+
+#[test]
+fn references_see_later_edits() {
+    expect(
+        [
+            ident!("marisa"),
+            poss!("s"),
+            ident!("age"),
+            kword!("is"),
+            num!(18.0),
+            //
+            ident!("reimu"),
+            poss!("s"),
+            ident!("friend"),
+            kword!("is"),
+            ident!("marisa"),
+            //
+            ident!("marisa"),
+            poss!("s"),
+            ident!("age"),
+            kword!("is"),
+            num!(19.0),
+        ],
+        [val_obj!(
+            "reimu",
+            val_obj!("friend", val_num!("age", 19.0))
+        )],
+    );
+
+    expect(
+        [
+            ident!("reimu"),
+            poss!("s"),
+            ident!("friend"),
+            kword!("is"),
+            ident!("marisa"),
+            //
+            ident!("marisa"),
+            poss!("s"),
+            ident!("age"),
+            kword!("is"),
+            num!(18.0),
+        ],
+        [val_obj!(
+            "reimu",
+            val_obj!("friend", val_num!("age", 18.0))
+        )],
+    );
+}
+
+#[test]
+#[should_panic(expected = "Cyclic reference: reimu -> marisa -> reimu")]
+fn detects_cyclic_references() {
+    parse(vec![
+        ident!("reimu"),
+        poss!("s"),
+        ident!("friend"),
+        kword!("is"),
+        ident!("marisa"),
+        //
+        ident!("marisa"),
+        poss!("s"),
+        ident!("friend"),
+        kword!("is"),
+        ident!("reimu"),
+    ]);
+}
+
+#[test]
+fn resolves_forward_references() {
+    expect(
+        [
+            ident!("player"),
+            kword!("is"),
+            ident!("Reimu"),
+            //
+            ident!("Reimu"),
+            poss!("s"),
+            ident!("age"),
+            kword!("is"),
+            num!(17.0),
+        ],
+        [
+            val_obj!("player", val_num!("age", 17.0)),
+            val_obj!("reimu", val_num!("age", 17.0)),
+        ],
+    );
+}
+
+#[test]
+#[should_panic(expected = "Undefined name `sakuya` in reimu's friend")]
+fn reports_undefined_names() {
+    parse(vec![
+        ident!("reimu"),
+        poss!("s"),
+        ident!("friend"),
+        kword!("is"),
+        ident!("sakuya"),
+    ]);
+}
+
+#[test]
+fn keeps_insertion_order() {
+    let result = parse(vec![
+        ident!("title"),
+        kword!("is"),
+        str!("The search of something"),
+        //
+        ident!("player"),
+        kword!("is"),
+        ident!("reimu"),
+        //
+        ident!("reimu"),
+        poss!("s"),
+        ident!("speed"),
+        kword!("is"),
+        num!(12.0),
+        //
+        ident!("reimu"),
+        poss!("s"),
+        ident!("image"),
+        kword!("is"),
+        str!("reimu.png"),
+        //
+        ident!("age"),
+        kword!("is"),
+        num!(17.0),
+    ]);
+
+    let keys: Vec<_> = result.keys().collect();
+    assert_eq!(keys, ["title", "player", "reimu", "age"]);
+
+    let Value::Object(reimu) = &result["reimu"] else {
+        panic!("Expected reimu to be an object");
+    };
+    let keys: Vec<_> = reimu.keys().collect();
+    assert_eq!(keys, ["speed", "image"]);
+}
+
+#[test]
+fn appends_to_lists() {
+    expect(
+        [
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+            kword!("are"),
+            str!("sword"),
+            str!("bow"),
+            //
+            kword!("add"),
+            str!("bomb"),
+            str!("card"),
+            kword!("to"),
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+            //
+            ident!("reimu"),
+            kword!("also"),
+            poss!("has"),
+            ident!("item"),
+            str!("charm"),
+        ],
+        [val_obj!(
+            "reimu",
+            val_list!("items", String, "sword", "bow", "bomb", "card", "charm")
+        )],
+    );
+
+    expect(
+        [
+            kword!("add"),
+            num!(1.0),
+            kword!("to"),
+            ident!("nums"),
+            poss!("of"),
+            ident!("marisa"),
+        ],
+        [val_obj!("marisa", val_list!("nums", Number, 1.))],
+    );
+}
+
+#[test]
+fn removes_from_lists() {
+    expect(
+        [
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+            kword!("are"),
+            str!("sword"),
+            str!("bow"),
+            str!("bomb"),
+            //
+            kword!("remove"),
+            ident!("bow"),
+            str!("bomb"),
+            kword!("from"),
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+        ],
+        [val_obj!("reimu", val_list!("items", String, "sword"))],
+    );
+}
+
+#[test]
+fn clears_lists() {
+    expect(
+        [
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+            kword!("are"),
+            str!("sword"),
+            str!("bow"),
+            //
+            kword!("clear"),
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+        ],
+        [(
+            "reimu".to_string(),
+            Value::Object(Object::from([("items".to_string(), Value::List(vec![]))])),
+        )],
+    );
+}
+
+#[test]
+fn forgets_properties() {
+    let result = parse(vec![
+        ident!("age"),
+        kword!("is"),
+        num!(17.0),
+        //
+        ident!("reimu"),
+        poss!("s"),
+        ident!("weapon"),
+        kword!("is"),
+        str!("bow"),
+        //
+        ident!("reimu"),
+        poss!("s"),
+        ident!("age"),
+        kword!("is"),
+        num!(17.0),
+        //
+        ident!("reimu"),
+        poss!("has"),
+        kword!("no"),
+        ident!("weapon"),
+        //
+        kword!("forget"),
+        ident!("age"),
+        poss!("of"),
+        ident!("reimu"),
+        //
+        kword!("forget"),
+        ident!("age"),
+    ]);
+
+    assert_eq!(result, Object::from([val_obj!("reimu",)]));
+}
+
+fn parse_files<const N: usize>(main: &str, files: [(&str, &str); N]) -> Object {
+    let files = HashMap::from(files);
+    parse_file(main, |path: &Path| {
+        files
+            .get(path.to_str().unwrap())
+            .map(|text| text.to_string())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    })
+}
+
+#[test]
+fn includes_files() {
+    let result = parse_files(
+        "game/main.th",
+        [
+            (
+                "game/main.th",
+                r#"include "stages/one.th". the fairy's health is 10, add "bomb" to reimu's items"#,
+            ),
+            (
+                "game/stages/one.th",
+                r#"include "../enemies.th". the title is "Stage one""#,
+            ),
+            (
+                "game/enemies.th",
+                r#"the fairy's health is 5, and the fairy's speed is 2. reimu's items are "bow""#,
+            ),
+        ],
+    );
+
+    assert_eq!(
+        result,
+        Object::from([
+            val_obj!("fairy", val_num!("health", 10.0), val_num!("speed", 2.0)),
+            val_obj!("reimu", val_list!("items", String, "bow", "bomb")),
+            val_str!("title", "Stage one"),
+        ])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Include cycle: a.th -> b.th -> a.th")]
+fn detects_include_cycles() {
+    parse_files(
+        "main.th",
+        [
+            ("main.th", r#"include "a.th""#),
+            ("a.th", r#"include "./b.th""#),
+            ("b.th", r#"include "a.th""#),
+        ],
+    );
+}
+
+#[test]
+fn copies_prototypes() {
+    let result = parse(vec![
+        ident!("fairy"),
+        kword!("is"),
+        kword!("like"),
+        ident!("enemy"),
+        pron!("its"),
+        ident!("health"),
+        kword!("is"),
+        num!(10.0),
+        //
+        ident!("enemy"),
+        poss!("s"),
+        ident!("health"),
+        kword!("is"),
+        num!(50.0),
+        //
+        pron!("its"),
+        ident!("speed"),
+        kword!("is"),
+        num!(2.0),
+    ]);
+
+    assert_eq!(
+        result,
+        Object::from([
+            val_obj!("fairy", val_num!("health", 10.0), val_num!("speed", 2.0)),
+            val_obj!("enemy", val_num!("health", 50.0), val_num!("speed", 2.0)),
+        ])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Undefined name `enemy` in fairy is like enemy")]
+fn reports_undefined_prototypes() {
+    parse(vec![
+        ident!("fairy"),
+        kword!("is"),
+        kword!("like"),
+        ident!("enemy"),
+    ]);
+}
+
+#[test]
+fn parses_sections() {
+    let result = parse(vec![
+        kword!("in"),
+        ident!("stage"),
+        ident!("one"),
+        punct!(":"),
+        ident!("title"),
+        kword!("is"),
+        str!("Easy"),
+        punct!(","),
+        ident!("boss"),
+        kword!("is"),
+        ident!("cirno"),
+        punct!("."),
+        //
+        ident!("title"),
+        kword!("is"),
+        str!("Game"),
+        //
+        ident!("cirno"),
+        kword!("is"),
+        str!("Global"),
+        //
+        kword!("in"),
+        ident!("stage"),
+        ident!("two"),
+        punct!(":"),
+        ident!("title"),
+        kword!("is"),
+        str!("Hard"),
+        punct!(","),
+        ident!("boss"),
+        kword!("is"),
+        ident!("cirno"),
+        punct!(","),
+        ident!("cirno"),
+        kword!("is"),
+        str!("Local"),
+    ]);
+
+    assert_eq!(
+        result,
+        Object::from([
+            val_obj!(
+                "stage one",
+                val_str!("title", "Easy"),
+                val_str!("boss", "Global")
+            ),
+            val_str!("title", "Game"),
+            val_str!("cirno", "Global"),
+            val_obj!(
+                "stage two",
+                val_str!("title", "Hard"),
+                val_str!("boss", "Local"),
+                val_str!("cirno", "Local")
+            ),
+        ])
+    );
+}
+
+#[test]
+#[should_panic(expected = "Expected `:` after section name")]
+fn sections_need_a_colon() {
+    parse(vec![
+        kword!("in"),
+        ident!("stage"),
+        ident!("one"),
+        kword!("is"),
+        str!("Easy"),
+    ]);
+}
+
+#[test]
+#[should_panic(
+    expected = "Cannot change constant `answer` at line 3, it is always what was set at line 1"
+)]
+fn constants_cannot_be_reassigned() {
+    parse(tokenize(
+        "the answer is always 42.
+        reimu's age is always 17.
+        the answer is 43.",
+    ));
+}
+
+#[test]
+#[should_panic(expected = "Cannot change constant `reimu's age` at line 2")]
+fn constant_properties_cannot_be_reassigned() {
+    parse(tokenize(
+        "the age of reimu is always 17.
+        forget reimu's age.",
+    ));
+}
+
+#[test]
+fn warns_on_redefinitions() {
+    let text = "the age is 17, and reimu's age is 16.
+        the age is 18.
+
+        reimu's age is age.";
+
+    let (_, warnings) = parse_with(tokenize(text), Lints::default());
+    assert!(warnings.is_empty());
+
+    let (result, warnings) = parse_with(
+        tokenize(text),
+        Lints {
+            redefinitions: true,
+        },
+    );
+    assert_eq!(
+        warnings,
+        [
+            "`age` is redefined at line 2, it was previously set at line 1",
+            "`reimu's age` is redefined at line 4, it was previously set at line 1",
+        ]
+    );
+    assert_eq!(result["age"], Value::Number(18.0));
+}
+
+#[test]
+fn checks_declared_types() {
+    let result = parse(tokenize(
+        r#"an enemy has a number called health, and a list of words called patterns.
+        the fairy is an enemy, its health is 10, and its patterns are "left" "right".
+        the boss is like the fairy, but her health is 200."#,
+    ));
+
+    assert_eq!(
+        result,
+        Object::from([
+            val_obj!(
+                "fairy",
+                val_num!("health", 10.0),
+                val_list!("patterns", String, "left", "right")
+            ),
+            val_obj!(
+                "boss",
+                val_num!("health", 200.0),
+                val_list!("patterns", String, "left", "right")
+            ),
+        ])
+    );
+}
+
+#[test]
+#[should_panic(
+    expected = "`fairy's health` should be a number like every enemy's, but is String(\"ten\") (set at line 3)"
+)]
+fn reports_type_mismatches() {
+    parse(tokenize(
+        r#"the fairy is an enemy.
+        an enemy has a number called health.
+        the fairy's health is "ten"."#,
+    ));
+}
+
+#[test]
+#[should_panic(expected = "`boss's patterns` should be a list of words like every enemy's, but is List([Number(1.0), Number(2.0)]) (set at line 2)")]
+fn reports_inherited_type_mismatches() {
+    parse(tokenize(
+        r#"the enemy has a list of words called patterns.
+        the enemy's patterns are 1 2.
+        the boss is like the enemy."#,
+    ));
+}
+
+#[test]
+fn finds_the_kinds_of_objects() {
+    let (result, kinds) = parse_kinds(tokenize(
+        r#"the fairy is an enemy, and its health is 10.
+        the wisp is an enemy, and its health is 3.
+        the boss is like the fairy, but her health is 200.
+        the cirno is like the boss."#,
+    ))
+    .unwrap();
+
+    assert_eq!(
+        result["wisp"],
+        Value::Object(Object::from([val_num!("health", 3.0)]))
+    );
+    assert_eq!(kinds["fairy"], ["enemy"]);
+    assert_eq!(kinds["wisp"], ["enemy"]);
+    assert_eq!(kinds["cirno"], ["boss", "fairy", "enemy"]);
+}
+
+#[test]
+fn declares_kinds_without_types() {
+    let (result, kinds) = parse_kinds(tokenize(
+        r#"the inner's x is 3. the fairy is an inner.
+        the wisp is an outer, and its x is 4. the ghost is an outer."#,
+    ))
+    .unwrap();
+
+    assert_eq!(result["fairy"], Value::Object(Object::from([val_num!("x", 3.0)])));
+    assert_eq!(kinds["fairy"], ["inner"]);
+    assert_eq!(kinds["wisp"], ["outer"]);
+    assert_eq!(kinds["ghost"], ["outer"]);
+    assert!(!kinds.contains_key("inner"));
+}
+
+#[test]
+fn finds_the_kinds_of_objects_in_sections() {
+    let (_, kinds) = parse_kinds(tokenize(
+        r#"the enemy's health is 10.
+        in stage one: the fairy is an enemy, and the wisp is like the fairy."#,
+    ))
+    .unwrap();
+
+    assert_eq!(kinds["stage one's fairy"], ["enemy"]);
+    assert_eq!(kinds["stage one's wisp"], ["fairy", "enemy"]);
+    assert!(!kinds.contains_key("fairy"));
+}
+
+#[test]
+fn parses_units_of_measure() {
+    let result = parse(tokenize(
+        r#"the delay is 2 seconds.
+        marisa has 200 health, and she has 3 seconds cooldown.
+        her size is 30 pixels, and her angles are 90 degrees, 45 degrees.
+        the opacity of marisa is 50%."#,
+    ));
+
+    assert_eq!(result["delay"], Value::Quantity(2.0, Unit::Seconds));
+    assert_eq!(
+        result["marisa"],
+        Value::Object(Object::from([
+            val_num!("health", 200.0),
+            ("cooldown".to_string(), Value::Quantity(3.0, Unit::Seconds)),
+            ("size".to_string(), Value::Quantity(30.0, Unit::Pixels)),
+            (
+                "angles".to_string(),
+                Value::List(vec![
+                    Value::Quantity(90.0, Unit::Degrees),
+                    Value::Quantity(45.0, Unit::Degrees),
+                ])
+            ),
+            ("opacity".to_string(), Value::Quantity(50.0, Unit::Percent)),
+        ]))
+    );
+}
+
+#[test]
+fn converts_compatible_units() {
+    assert_eq!(Unit::Milliseconds.convert(1500.0, Unit::Seconds), Some(1.5));
+    assert_eq!(Unit::Seconds.convert(2.0, Unit::Milliseconds), Some(2000.0));
+    assert_eq!(Unit::Pixels.convert(30.0, Unit::Seconds), None);
+}
+
+#[test]
+fn parses_times() {
+    let result = parse(tokenize(
+        r#"the boss is at 1:30.
+        the fairy's delay is after 3 seconds, and her rate is every half a second.
+        the spawn of the fairy is at 45, and her blink is every 10 frames."#,
+    ));
+
+    assert_eq!(result["boss"], Value::Quantity(90.0, Unit::Seconds));
+    assert_eq!(
+        result["fairy"],
+        Value::Object(Object::from([
+            ("delay".to_string(), Value::Quantity(3.0, Unit::Seconds)),
+            ("rate".to_string(), Value::Quantity(0.5, Unit::Seconds)),
+            ("spawn".to_string(), Value::Quantity(45.0, Unit::Seconds)),
+            ("blink".to_string(), Value::Quantity(10.0, Unit::Frames)),
+        ]))
+    );
+}
+
+#[test]
+#[should_panic(expected = "Unexpected token pattern")]
+fn times_must_measure_time() {
+    parse(tokenize("the fairy's delay is after 3 pixels."));
+}
+
+#[test]
+fn parses_lists_of_words() {
+    let result = parse(tokenize(
+        r#"an enemy has a list of words called pattern, and marisa has a word called state.
+        the enemy's pattern are left right, Shoot_Up down, marisa's state is hidden."#,
+    ));
+
+    assert_eq!(
+        result,
+        Object::from([
+            val_obj!("enemy", val_list!("pattern", Word, "left", "right", "shoot_up", "down")),
+            val_obj!("marisa", ("state".to_string(), Value::Word("hidden".to_string()))),
+        ])
+    );
+}
+
+#[test]
+fn parses_nothing() {
+    let result = parse(tokenize(
+        r#"the boss is nothing, marisa's item is none, and her friends are reimu nothing.
+        reimu is "Reimu"."#,
+    ));
+
+    assert_eq!(result["boss"], Value::Nothing);
+    assert_eq!(
+        result["marisa"],
+        Value::Object(Object::from([
+            ("item".to_string(), Value::Nothing),
+            (
+                "friends".to_string(),
+                Value::List(vec![Value::String("Reimu".to_string()), Value::Nothing])
+            ),
+        ]))
+    );
+}
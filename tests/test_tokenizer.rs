@@ -5,6 +5,8 @@ token_macro!(num, Number);
 token_macro!(kword, Keyword);
 token_macro!(poss, Possesive);
 token_macro!(str, String);
+token_macro!(pron, Pronoun);
+token_macro!(punct, Punctuation);
 
 fn expect<const N: usize>(text: &str, tokens: [Token; N]) {
     assert_eq!(tokenize(text), tokens);
@@ -73,6 +75,7 @@ fn recognizes_multiple_phrases() {
             ident!("age"),
             kword!("is"),
             num!(17.0),
+            punct!(","),
             //
             ident!("marisa"),
             poss!("s"),
@@ -83,3 +86,90 @@ fn recognizes_multiple_phrases() {
     )
 }
 
+#[test]
+fn recognizes_list_keywords() {
+    expect(
+        r#"add "bomb" to reimu's items"#,
+        [
+            kword!("add"),
+            str!("bomb"),
+            kword!("to"),
+            ident!("reimu"),
+            poss!("s"),
+            ident!("items"),
+        ],
+    );
+    expect(
+        "reimu also has",
+        [ident!("reimu"), kword!("also"), poss!("has")],
+    );
+}
+
+#[test]
+fn recognizes_pronouns() {
+    expect(
+        "her damage is 12",
+        [pron!("her"), ident!("damage"), kword!("is"), num!(12.0)],
+    );
+}
+
+#[test]
+fn keeps_punctuation_after_words() {
+    expect(
+        "in stage one: age is 17. x is 1.5",
+        [
+            kword!("in"),
+            ident!("stage"),
+            ident!("one"),
+            punct!(":"),
+            ident!("age"),
+            kword!("is"),
+            num!(17.0),
+            punct!("."),
+            ident!("x"),
+            kword!("is"),
+            num!(1.5),
+        ],
+    );
+}
+
+#[test]
+fn recognizes_newlines() {
+    expect(
+        "\n age is 17\n\n",
+        [
+            Token::Newline,
+            ident!("age"),
+            kword!("is"),
+            num!(17.0),
+        ],
+    );
+}
+
+#[test]
+fn reads_percent_as_a_word() {
+    expect(
+        "speed is 50%",
+        [ident!("speed"), kword!("is"), num!(50.0), ident!("%")],
+    );
+}
+
+#[test]
+fn recognizes_times() {
+    token_macro!(time, Time);
+
+    expect(
+        "at 1:30, at 1:02:03. in stage:",
+        [
+            kword!("at"),
+            time!(90.0),
+            punct!(","),
+            kword!("at"),
+            time!(3723.0),
+            punct!("."),
+            kword!("in"),
+            ident!("stage"),
+            punct!(":"),
+        ],
+    );
+}